@@ -1,7 +1,7 @@
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-use crate::{AggregateError, DomainEvent};
+use crate::{AggregateError, DomainEvent, ValidationError};
 
 /// In CQRS (and Domain Driven Design) an `Aggregate` is the fundamental component that
 /// encapsulates the state and application logic (aka business rules) for the application.
@@ -107,4 +107,143 @@ pub trait Aggregate: Default + Serialize + DeserializeOwned + Sync + Send {
     /// }
     /// ```
     fn apply(&mut self, event: Self::Event);
+    /// Builds a new aggregate instance by applying the provided events, in order, to the default
+    /// aggregate.
+    ///
+    /// ```
+    /// # use cqrs_es::doc::{Customer, CustomerEvent};
+    /// use cqrs_es::Aggregate;
+    ///
+    /// let customer = Customer::hydrate_from_events(vec![CustomerEvent::NameAdded {
+    ///     changed_name: "John Doe".to_string(),
+    /// }]);
+    /// assert_eq!("John Doe", customer.name);
+    /// ```
+    fn hydrate_from_events(events: Vec<Self::Event>) -> Self {
+        let mut aggregate = Self::default();
+        for event in events {
+            aggregate.apply(event);
+        }
+        aggregate
+    }
+    /// Provides the ID of this aggregate instance, if the aggregate is aware of it.
+    ///
+    /// By default aggregates are not aware of their own ID and this returns `None`. An aggregate
+    /// that needs its own ID, e.g., to include it in the payload of emitted events, should
+    /// override this along with `set_aggregate_id`.
+    ///
+    /// ```ignore
+    /// fn aggregate_id(&self) -> Option<&str> {
+    ///     Some(&self.customer_id)
+    /// }
+    /// ```
+    fn aggregate_id(&self) -> Option<&str> {
+        None
+    }
+    /// Called with the ID of the aggregate instance when it is loaded, before any events are
+    /// applied. By default the ID is ignored.
+    ///
+    /// ```ignore
+    /// fn set_aggregate_id(&mut self, aggregate_id: &str) {
+    ///     self.customer_id = aggregate_id.to_string();
+    /// }
+    /// ```
+    fn set_aggregate_id(&mut self, _aggregate_id: &str) {}
+    /// Called by the [CqrsFramework](struct.CqrsFramework.html) the first time a command succeeds
+    /// for an aggregate instance, i.e., when events are committed for an aggregate instance that
+    /// had no events when it was loaded. A retried command that the event store reports as a
+    /// duplicate does not call this again. The aggregate provided has the committed events
+    /// applied. By default this does nothing.
+    ///
+    /// ```ignore
+    /// fn on_create(&self) {
+    ///     println!("customer created: {}", self.customer_id);
+    /// }
+    /// ```
+    fn on_create(&self) {}
+}
+
+/// A `CommandHandler` holds command handling logic separately from the `Aggregate`, allowing the
+/// logic to be injected into a [CqrsFramework](struct.CqrsFramework.html) and to be unit tested
+/// against an aggregate instance directly.
+///
+/// When no command handler is provided the framework uses `Aggregate::handle`.
+///
+/// # Examples
+/// ```rust
+/// # use cqrs_es::doc::{Customer, CustomerEvent, CustomerCommand};
+/// # use cqrs_es::{AggregateError, CommandHandler};
+/// struct CustomerCommandHandler;
+///
+/// impl CommandHandler<Customer> for CustomerCommandHandler {
+///     fn handle(
+///         &self,
+///         customer: &Customer,
+///         command: CustomerCommand,
+///     ) -> Result<Vec<CustomerEvent>, AggregateError> {
+///         match command {
+///             CustomerCommand::AddCustomerName { changed_name } => {
+///                 if !customer.name.is_empty() {
+///                     return Err(AggregateError::new("a name has already been added"));
+///                 }
+///                 Ok(vec![CustomerEvent::NameAdded { changed_name }])
+///             }
+///             CustomerCommand::UpdateEmail { new_email } => {
+///                 Ok(vec![CustomerEvent::EmailUpdated { new_email }])
+///             }
+///         }
+///     }
+/// }
+///
+/// let events = CustomerCommandHandler.handle(
+///     &Customer::default(),
+///     CustomerCommand::UpdateEmail { new_email: "john@example.com".to_string() },
+/// );
+/// assert!(events.is_ok());
+/// ```
+pub trait CommandHandler<A>: Send + Sync
+where
+    A: Aggregate,
+{
+    /// Processes the command against the current state of the aggregate, returning either the
+    /// events to be committed or an error if the command is rejected.
+    fn handle(&self, aggregate: &A, command: A::Command) -> Result<Vec<A::Event>, AggregateError>;
+}
+
+/// A `CommandValidator` performs schema-level validation of a command, e.g., that a required
+/// field is not empty, before the command is handled. This separates simple validation from the
+/// domain invariants enforced by the aggregate.
+///
+/// Validators are added to a [CqrsFramework](struct.CqrsFramework.html) with `with_validator`,
+/// a command that fails validation is rejected with an `AggregateError::ValidationError` before
+/// the aggregate is loaded.
+///
+/// # Examples
+/// ```rust
+/// # use cqrs_es::doc::{Customer, CustomerCommand};
+/// # use cqrs_es::{CommandValidator, ValidationError};
+/// struct CustomerCommandValidator;
+///
+/// impl CommandValidator<Customer> for CustomerCommandValidator {
+///     fn validate(&self, command: &CustomerCommand) -> Result<(), ValidationError> {
+///         match command {
+///             CustomerCommand::AddCustomerName { changed_name } if changed_name.is_empty() => {
+///                 Err(ValidationError::for_field("changed_name", "name must not be empty"))
+///             }
+///             _ => Ok(()),
+///         }
+///     }
+/// }
+///
+/// let result = CustomerCommandValidator.validate(&CustomerCommand::AddCustomerName {
+///     changed_name: "".to_string(),
+/// });
+/// assert!(result.is_err());
+/// ```
+pub trait CommandValidator<A>: Send + Sync
+where
+    A: Aggregate,
+{
+    /// Validates the command, returning an error describing the first problem found.
+    fn validate(&self, command: &A::Command) -> Result<(), ValidationError>;
 }
@@ -0,0 +1,98 @@
+use std::marker::PhantomData;
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+
+use crate::event::EventEnvelope;
+use crate::{Aggregate, AggregateError, Query};
+
+/// A record of the outcome of a single command, see `CommandAuditLog`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    /// The ID of the aggregate instance the command was applied to.
+    pub aggregate_id: String,
+    /// The number of events committed by the command, zero if the command failed.
+    pub event_count: usize,
+    /// The time at which the outcome was recorded.
+    pub timestamp: SystemTime,
+    /// A description of the error if the command failed.
+    pub error: Option<String>,
+}
+
+/// Records the outcome of every command in a shared list, allowing tests to verify how many
+/// commands were processed and whether they succeeded.
+///
+/// As a `Query` the log records each successful command, to also record failed commands it must
+/// be added as an error observer of the `CqrsFramework`. Clones of the log share the same entries.
+///
+/// ```
+/// # use cqrs_es::doc::MyAggregate;
+/// use std::sync::Arc;
+/// use cqrs_es::{CommandAuditLog, CqrsFramework};
+/// use cqrs_es::mem_store::MemStore;
+///
+/// let audit_log = CommandAuditLog::<MyAggregate>::default();
+/// let error_log = audit_log.clone();
+/// let store = MemStore::<MyAggregate>::default();
+/// let cqrs = CqrsFramework::new(store, vec![Arc::new(audit_log.clone())])
+///     .with_error_observer(move |aggregate_id, err| error_log.record_error(aggregate_id, err));
+/// //...
+/// let entries = audit_log.entries();
+/// ```
+pub struct CommandAuditLog<A: Aggregate> {
+    entries: Arc<RwLock<Vec<AuditEntry>>>,
+    _phantom: PhantomData<fn() -> A>,
+}
+
+impl<A: Aggregate> Default for CommandAuditLog<A> {
+    fn default() -> Self {
+        CommandAuditLog {
+            entries: Default::default(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<A: Aggregate> Clone for CommandAuditLog<A> {
+    fn clone(&self) -> Self {
+        CommandAuditLog {
+            entries: self.entries.clone(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<A: Aggregate> CommandAuditLog<A> {
+    /// Records a failed command.
+    pub fn record_error(&self, aggregate_id: &str, err: &AggregateError) {
+        self.record(AuditEntry {
+            aggregate_id: aggregate_id.to_string(),
+            event_count: 0,
+            timestamp: SystemTime::now(),
+            error: Some(err.to_string()),
+        });
+    }
+    /// A copy of all entries recorded so far, in the order in which they were recorded.
+    pub fn entries(&self) -> Vec<AuditEntry> {
+        // uninteresting unwrap: this will not be used in production, for tests only
+        self.entries.read().unwrap().clone()
+    }
+
+    fn record(&self, entry: AuditEntry) {
+        // uninteresting unwrap: this will not be used in production, for tests only
+        self.entries.write().unwrap().push(entry);
+    }
+}
+
+#[async_trait]
+impl<A: Aggregate> Query<A> for CommandAuditLog<A> {
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<A>]) {
+        self.record(AuditEntry {
+            aggregate_id: aggregate_id.to_string(),
+            event_count: events.len(),
+            timestamp: SystemTime::now(),
+            error: None,
+        });
+    }
+}
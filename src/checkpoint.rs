@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+
+use crate::event::EventEnvelope;
+use crate::{Aggregate, Query};
+
+/// Persists the progress of a projection, the sequence number of the last event processed for
+/// each aggregate instance. A separate checkpoint store should be used for each projection.
+#[async_trait]
+pub trait CheckpointStore: Send + Sync {
+    /// Records that all events up to and including `sequence` have been processed for the
+    /// aggregate instance.
+    async fn save_checkpoint(&self, aggregate_id: &str, sequence: usize);
+    /// The sequence number of the last event processed for the aggregate instance, or `None` if
+    /// no events have been processed.
+    async fn load_checkpoint(&self, aggregate_id: &str) -> Option<usize>;
+}
+
+/// Simple in-memory checkpoint store useful for application development and testing purposes.
+/// Clones of the store share the same checkpoints.
+///
+/// ```
+/// use cqrs_es::MemCheckpointStore;
+///
+/// let checkpoints = MemCheckpointStore::default();
+/// //...
+/// let checkpoint_map = checkpoints.get_checkpoints();
+/// ```
+#[derive(Default, Clone)]
+pub struct MemCheckpointStore {
+    checkpoints: Arc<RwLock<HashMap<String, usize>>>,
+}
+
+impl MemCheckpointStore {
+    /// Get a shared copy of the checkpoints, keyed by aggregate ID.
+    pub fn get_checkpoints(&self) -> Arc<RwLock<HashMap<String, usize>>> {
+        Arc::clone(&self.checkpoints)
+    }
+}
+
+#[async_trait]
+impl CheckpointStore for MemCheckpointStore {
+    async fn save_checkpoint(&self, aggregate_id: &str, sequence: usize) {
+        // uninteresting unwrap: this will not be used in production, for tests only
+        let mut checkpoints = self.checkpoints.write().unwrap();
+        checkpoints.insert(aggregate_id.to_string(), sequence);
+    }
+
+    async fn load_checkpoint(&self, aggregate_id: &str) -> Option<usize> {
+        // uninteresting unwrap: this will not be used in production, for tests only
+        let checkpoints = self.checkpoints.read().unwrap();
+        checkpoints.get(aggregate_id).copied()
+    }
+}
+
+/// A query wrapper that records a checkpoint after each successful dispatch, allowing a
+/// projection rebuild that is interrupted to resume from where it left off.
+///
+/// Events at or before the checkpoint of their aggregate instance are not dispatched to the
+/// wrapped query, so a rebuild may simply replay all events again after an interruption.
+///
+/// ```
+/// # use cqrs_es::doc::Customer;
+/// # use cqrs_es::{EventEnvelope, Query};
+/// # use async_trait::async_trait;
+/// use std::sync::Arc;
+/// use cqrs_es::{Checkpointed, CqrsFramework, MemCheckpointStore};
+/// use cqrs_es::mem_store::MemStore;
+///
+/// # struct CustomerNameQuery;
+/// # #[async_trait]
+/// # impl Query<Customer> for CustomerNameQuery {
+/// #     async fn dispatch(&self, _aggregate_id: &str, _events: &[EventEnvelope<Customer>]) {}
+/// # }
+/// let query = Checkpointed::new(CustomerNameQuery, MemCheckpointStore::default());
+/// let store = MemStore::<Customer>::default();
+/// let cqrs = CqrsFramework::new(store, vec![Arc::new(query)]);
+/// ```
+pub struct Checkpointed<Q, C> {
+    query: Q,
+    checkpoints: C,
+}
+
+impl<Q, C> Checkpointed<Q, C> {
+    /// Wraps the provided query, recording its progress in the checkpoint store.
+    pub fn new(query: Q, checkpoints: C) -> Self {
+        Checkpointed { query, checkpoints }
+    }
+}
+
+#[async_trait]
+impl<A, Q, C> Query<A> for Checkpointed<Q, C>
+where
+    A: Aggregate,
+    Q: Query<A>,
+    C: CheckpointStore,
+{
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<A>]) {
+        let checkpoint = self.checkpoints.load_checkpoint(aggregate_id).await;
+        let unprocessed = match checkpoint {
+            Some(checkpoint) => {
+                let start = events
+                    .iter()
+                    .position(|event| event.sequence > checkpoint)
+                    .unwrap_or(events.len());
+                &events[start..]
+            }
+            None => events,
+        };
+        let last_sequence = match unprocessed.last() {
+            Some(event) => event.sequence,
+            None => return,
+        };
+        self.query.dispatch(aggregate_id, unprocessed).await;
+        self.checkpoints
+            .save_checkpoint(aggregate_id, last_sequence)
+            .await;
+    }
+
+    fn subscribe_to_all(&self) -> bool {
+        self.query.subscribe_to_all()
+    }
+
+    fn subscribes_to(&self, aggregate_id: &str) -> bool {
+        self.query.subscribes_to(aggregate_id)
+    }
+
+    fn name(&self) -> &'static str {
+        self.query.name()
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+
+use crate::aggregate::Aggregate;
+
+/// The metadata key under which the ID of the originating command is recorded on each committed
+/// `EventEnvelope`.
+pub const COMMAND_ID_METADATA_KEY: &str = "command_id";
+
+/// The metadata key under which the idempotency key of the originating command is recorded on
+/// each committed `EventEnvelope`.
+pub const IDEMPOTENCY_KEY_METADATA_KEY: &str = "idempotency_key";
+
+/// The metadata key under which the correlation ID of the originating command is recorded on
+/// each committed `EventEnvelope`.
+pub const CORRELATION_ID_METADATA_KEY: &str = "correlation_id";
+
+/// `CommandContext` holds all per-command, cross-cutting information that should accompany a
+/// command, e.g., audit metadata or identifiers used for idempotency and tracing.
+///
+/// All of this information is recorded in the metadata of any events produced by the command.
+///
+/// ```
+/// use cqrs_es::CommandContext;
+///
+/// let context = CommandContext::default()
+///     .with_metadata_entry("user", "alice")
+///     .with_command_id("8A3B9C6D")
+///     .with_correlation_id("request-5F1E");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommandContext {
+    /// Metadata to be attached to any produced events.
+    pub metadata: HashMap<String, String>,
+    /// An optional unique ID for the command, used to detect duplicate submissions.
+    pub command_id: Option<String>,
+    /// An optional key supplied by the client to make retries of the same request idempotent.
+    pub idempotency_key: Option<String>,
+    /// An optional ID used to correlate this command with other activity, e.g., an inbound request.
+    pub correlation_id: Option<String>,
+}
+
+impl CommandContext {
+    /// Sets the metadata to be attached to any produced events, replacing any existing metadata.
+    #[must_use]
+    pub fn with_metadata(mut self, metadata: HashMap<String, String>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+    /// Adds a single entry to the metadata to be attached to any produced events.
+    #[must_use]
+    pub fn with_metadata_entry(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+    /// Sets the unique ID of the command.
+    #[must_use]
+    pub fn with_command_id(mut self, command_id: impl Into<String>) -> Self {
+        self.command_id = Some(command_id.into());
+        self
+    }
+    /// Sets the idempotency key of the command.
+    #[must_use]
+    pub fn with_idempotency_key(mut self, idempotency_key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(idempotency_key.into());
+        self
+    }
+    /// Sets the correlation ID of the command.
+    #[must_use]
+    pub fn with_correlation_id(mut self, correlation_id: impl Into<String>) -> Self {
+        self.correlation_id = Some(correlation_id.into());
+        self
+    }
+    /// Consumes the context, providing the metadata with any identifiers added under their
+    /// respective metadata keys.
+    pub fn into_metadata(self) -> HashMap<String, String> {
+        let mut metadata = self.metadata;
+        let identifiers = [
+            (COMMAND_ID_METADATA_KEY, self.command_id),
+            (IDEMPOTENCY_KEY_METADATA_KEY, self.idempotency_key),
+            (CORRELATION_ID_METADATA_KEY, self.correlation_id),
+        ];
+        for (key, value) in identifiers {
+            if let Some(value) = value {
+                metadata.insert(key.to_string(), value);
+            }
+        }
+        metadata
+    }
+}
+
+/// `CommandEnvelope` packages a command along with the information needed to process it.
+///
+/// A command ID may be provided to make the command idempotent, if the same command is submitted
+/// more than once (e.g., due to a network retry) a store supporting idempotent commits will
+/// recognize the duplicate and return the previously committed events rather than committing
+/// them again. The command ID is recorded in the metadata of each committed event under the
+/// [`COMMAND_ID_METADATA_KEY`].
+///
+/// ```
+/// # use cqrs_es::doc::{MyAggregate, MyCommands};
+/// use cqrs_es::CommandEnvelope;
+///
+/// let envelope = CommandEnvelope::<MyAggregate>::new(MyCommands::DoSomething)
+///     .with_command_id("8A3B9C6D".to_string());
+/// ```
+pub struct CommandEnvelope<A>
+where
+    A: Aggregate,
+{
+    /// The command to be handled by the aggregate.
+    pub command: A::Command,
+    /// Metadata to be attached to any produced events.
+    pub metadata: HashMap<String, String>,
+    /// An optional unique ID for this command, used to detect duplicate submissions.
+    pub command_id: Option<String>,
+}
+
+impl<A: Aggregate> CommandEnvelope<A> {
+    /// Packages a command with no metadata and no command ID.
+    pub fn new(command: A::Command) -> Self {
+        CommandEnvelope {
+            command,
+            metadata: Default::default(),
+            command_id: None,
+        }
+    }
+    /// Sets the metadata to be attached to any produced events.
+    #[must_use]
+    pub fn with_metadata(mut self, metadata: HashMap<String, String>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+    /// Sets the unique ID of this command.
+    #[must_use]
+    pub fn with_command_id(mut self, id: String) -> Self {
+        self.command_id = Some(id);
+        self
+    }
+    /// Splits the envelope into the command and the context it should be executed with.
+    pub fn into_parts(self) -> (A::Command, CommandContext) {
+        let context = CommandContext {
+            metadata: self.metadata,
+            command_id: self.command_id,
+            ..Default::default()
+        };
+        (self.command, context)
+    }
+}
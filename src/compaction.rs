@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+
+use crate::event::{DomainEvent, EventEnvelope};
+use crate::{Aggregate, AggregateError, CountableEventStore, EventEnvelopeStream, EventStore};
+
+/// An aggregate whose state may be carried by a single event, allowing a prefix of its event
+/// stream to be compacted by a [`CompactingEventStore`].
+pub trait CompactableAggregate: Aggregate {
+    /// Produces an event that, when applied to a default aggregate, restores the current state of
+    /// this aggregate. Usually a dedicated event variant holding the serialized state.
+    ///
+    /// ```ignore
+    /// fn compaction_event(&self) -> Self::Event {
+    ///     CustomerEvent::Compacted {
+    ///         state: serde_json::to_value(self).unwrap(),
+    ///     }
+    /// }
+    /// ```
+    fn compaction_event(&self) -> Self::Event;
+}
+
+/// An event store that allows the events of an aggregate instance to be replaced, as is
+/// required for compaction.
+#[async_trait]
+pub trait CompactableEventStore<A>: EventStore<A>
+where
+    A: Aggregate,
+{
+    /// Replaces the events of the aggregate instance up to and including the event with the
+    /// sequence `through_sequence` with the provided events, retaining all later events, including
+    /// any committed since the replaced events were loaded.
+    ///
+    /// An `AggregateError::AggregateConflict` is returned, and no events are replaced, if the
+    /// aggregate instance has no event with the sequence `through_sequence`, e.g., because the
+    /// events were replaced concurrently.
+    async fn replace_events(
+        &self,
+        aggregate_id: &str,
+        through_sequence: usize,
+        events: Vec<EventEnvelope<A>>,
+    ) -> Result<(), AggregateError>;
+}
+
+/// An event store wrapper that compacts event streams that grow indefinitely, replacing all but
+/// the most recent events of an aggregate instance with a single state-carrying event.
+///
+/// Compaction is performed by calling `compact`, e.g., periodically from a background task. The
+/// events before the watermark are applied to a default aggregate and replaced with the event
+/// from [`CompactableAggregate::compaction_event`], which takes the sequence number of the last
+/// event that it replaces. Before the store is modified the compacted stream is replayed, and
+/// the compaction is abandoned with an `AggregateError::TechnicalError` if this does not produce
+/// the same aggregate state. Only the compacted events are replaced, events committed while the
+/// compaction is in progress are retained.
+///
+/// ```ignore
+/// let store = CompactingEventStore::new(MemStore::<Customer>::default(), 100);
+/// //...
+/// store.compact("agg-id-F39A0C").await?;
+/// ```
+pub struct CompactingEventStore<ES> {
+    store: ES,
+    watermark: usize,
+}
+
+impl<ES> CompactingEventStore<ES> {
+    /// Wraps the provided event store, retaining the most recent `watermark` events of each
+    /// aggregate instance when compacting.
+    pub fn new(store: ES, watermark: usize) -> Self {
+        CompactingEventStore { store, watermark }
+    }
+    /// Compacts the events of the aggregate instance, returning `false` if there are too few
+    /// events to compact.
+    pub async fn compact<A>(&self, aggregate_id: &str) -> Result<bool, AggregateError>
+    where
+        A: CompactableAggregate,
+        ES: CompactableEventStore<A>,
+    {
+        let mut events = self.store.load(aggregate_id).await;
+        if events.len() <= self.watermark + 1 {
+            return Ok(false);
+        }
+        let retained_events = events.split_off(events.len() - self.watermark);
+        let expected_state = serialized_state::<A>(
+            events
+                .iter()
+                .chain(retained_events.iter())
+                .map(|envelope| envelope.payload.clone())
+                .collect(),
+        )?;
+        // uninteresting unwrap: at least two events remain to be compacted
+        let last_compacted = events.last().cloned().unwrap();
+        let compacted_state = A::hydrate_from_events(
+            events
+                .into_iter()
+                .map(|envelope| envelope.payload)
+                .collect(),
+        );
+        let compaction_event = compacted_state.compaction_event();
+        let through_sequence = last_compacted.sequence;
+        let compaction_envelope = EventEnvelope {
+            payload: compaction_event.clone(),
+            event_type: compaction_event.event_type().to_string(),
+            event_version: compaction_event.event_version().to_string(),
+            ..last_compacted
+        };
+        let replayed_state = serialized_state::<A>(
+            std::iter::once(&compaction_envelope)
+                .chain(retained_events.iter())
+                .map(|envelope| envelope.payload.clone())
+                .collect(),
+        )?;
+        if replayed_state != expected_state {
+            return Err(AggregateError::TechnicalError(format!(
+                "compaction of aggregate '{}' does not reproduce the aggregate state",
+                aggregate_id
+            )));
+        }
+        self.store
+            .replace_events(aggregate_id, through_sequence, vec![compaction_envelope])
+            .await?;
+        Ok(true)
+    }
+}
+
+fn serialized_state<A: Aggregate>(
+    events: Vec<A::Event>,
+) -> Result<serde_json::Value, AggregateError> {
+    Ok(serde_json::to_value(A::hydrate_from_events(events))?)
+}
+
+#[async_trait]
+impl<A, ES> EventStore<A> for CompactingEventStore<ES>
+where
+    A: Aggregate + 'static,
+    ES: EventStore<A> + 'static,
+{
+    type AC = ES::AC;
+
+    async fn load(&self, aggregate_id: &str) -> Vec<EventEnvelope<A>> {
+        self.store.load(aggregate_id).await
+    }
+
+    fn load_stream<'a>(&'a self, aggregate_id: &'a str) -> EventEnvelopeStream<'a, A>
+    where
+        A: 'a,
+    {
+        self.store.load_stream(aggregate_id)
+    }
+
+    async fn load_aggregate(&self, aggregate_id: &str) -> Self::AC {
+        self.store.load_aggregate(aggregate_id).await
+    }
+
+    async fn last_event_version(&self, aggregate_id: &str) -> Option<usize> {
+        self.store.last_event_version(aggregate_id).await
+    }
+
+    async fn is_duplicate_command(&self, aggregate_id: &str, command_id: &str) -> bool {
+        self.store
+            .is_duplicate_command(aggregate_id, command_id)
+            .await
+    }
+
+    async fn commit(
+        &self,
+        events: Vec<A::Event>,
+        context: Self::AC,
+        metadata: HashMap<String, String>,
+    ) -> Result<Vec<EventEnvelope<A>>, AggregateError> {
+        self.store.commit(events, context, metadata).await
+    }
+}
+
+#[async_trait]
+impl<A, ES> CountableEventStore<A> for CompactingEventStore<ES>
+where
+    A: Aggregate + 'static,
+    ES: CountableEventStore<A> + 'static,
+{
+    async fn count_events(&self) -> usize {
+        self.store.count_events().await
+    }
+}
@@ -0,0 +1,68 @@
+use crate::event::EventEnvelope;
+use crate::{Aggregate, AggregateError, CqrsFramework, EventStore};
+
+/// Produces the command that reverses a previously committed event, for use when a later step of
+/// a saga fails and the earlier steps must be rolled back.
+///
+/// ```ignore
+/// impl Compensator<Inventory> for InventoryCompensator {
+///     fn compensate(&self, event: &EventEnvelope<Inventory>) -> Option<(String, InventoryCommand)> {
+///         match &event.payload {
+///             InventoryEvent::Reserved { quantity } => Some((
+///                 event.aggregate_id.clone(),
+///                 InventoryCommand::Release { quantity: *quantity },
+///             )),
+///             _ => None,
+///         }
+///     }
+/// }
+/// ```
+pub trait Compensator<A>: Send + Sync
+where
+    A: Aggregate,
+{
+    /// Returns the aggregate ID and command that reverse the event, or `None` if the event
+    /// requires no compensation.
+    fn compensate(&self, event: &EventEnvelope<A>) -> Option<(String, A::Command)>;
+}
+
+/// Rolls back a series of committed events by executing the compensating command for each, in
+/// the reverse of the order in which the events were committed.
+///
+/// ```ignore
+/// let runner = CompensatingCommandRunner::new(InventoryCompensator);
+/// let results = runner.run(&inventory_cqrs, &reserved_events).await;
+/// ```
+pub struct CompensatingCommandRunner<C> {
+    compensator: C,
+}
+
+impl<C> CompensatingCommandRunner<C> {
+    /// Creates a runner using the provided compensator.
+    pub fn new(compensator: C) -> Self {
+        CompensatingCommandRunner { compensator }
+    }
+    /// Executes the compensating commands for the events, latest event first. A failure of one
+    /// compensating command does not prevent the remaining commands from being executed, the
+    /// aggregate ID targeted by each command is returned along with its result, in the order in
+    /// which the commands were executed.
+    pub async fn run<A, ES>(
+        &self,
+        cqrs: &CqrsFramework<A, ES>,
+        events: &[EventEnvelope<A>],
+    ) -> Vec<(String, Result<(), AggregateError>)>
+    where
+        A: Aggregate,
+        ES: EventStore<A>,
+        C: Compensator<A>,
+    {
+        let mut results = Vec::new();
+        for event in events.iter().rev() {
+            if let Some((aggregate_id, command)) = self.compensator.compensate(event) {
+                let result = cqrs.execute(&aggregate_id, command).await;
+                results.push((aggregate_id, result));
+            }
+        }
+        results
+    }
+}
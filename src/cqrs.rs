@@ -1,10 +1,40 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::sync::Arc;
+use std::time::Duration;
 
+use futures::future::join_all;
+use tokio::sync::broadcast;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::command::{CommandContext, CommandEnvelope, COMMAND_ID_METADATA_KEY};
+use crate::event::EventEnvelope;
 use crate::query::Query;
+use crate::shutdown::{InFlightCommands, ShutdownHandle};
 use crate::store::EventStore;
 use crate::AggregateContext;
-use crate::{Aggregate, AggregateError};
+use crate::{
+    Aggregate, AggregateError, CommandDispatchError, CommandHandler, CommandRateLimiter,
+    CommandValidator, QueryError,
+};
+
+// The number of committed events retained for subscribers, a subscriber that falls further behind
+// will receive a `RecvError::Lagged` and miss the oldest events.
+const SUBSCRIBER_CAPACITY: usize = 1024;
+
+type EventObserver<A> = Box<dyn Fn(&[EventEnvelope<A>]) + Send + Sync>;
+type ErrorObserver = Box<dyn Fn(&str, &AggregateError) + Send + Sync>;
+type StateInspector<A> = Box<dyn Fn(&str, &A, &A) + Send + Sync>;
+
+// The command handler used when none is provided, delegating to the aggregate itself.
+struct AggregateCommandHandler;
+
+impl<A: Aggregate> CommandHandler<A> for AggregateCommandHandler {
+    fn handle(&self, aggregate: &A, command: A::Command) -> Result<Vec<A::Event>, AggregateError> {
+        aggregate.handle(command)
+    }
+}
 
 /// This is the base framework for applying commands to produce events.
 ///
@@ -26,6 +56,31 @@ where
 {
     store: ES,
     query_processors: Vec<Arc<dyn Query<A>>>,
+    command_handler: Box<dyn CommandHandler<A>>,
+    validators: Vec<Box<dyn CommandValidator<A>>>,
+    observers: Vec<EventObserver<A>>,
+    error_observers: Vec<ErrorObserver>,
+    inspectors: Vec<StateInspector<A>>,
+    query_timeout: Option<Duration>,
+    rate_limiter: Option<CommandRateLimiter>,
+    max_events_per_aggregate: Option<usize>,
+    subscribers: broadcast::Sender<EventEnvelope<A>>,
+    in_flight: Arc<InFlightCommands>,
+}
+
+// Only the type of the store and the number of queries are shown, the store itself may hold
+// connection details that should not be logged.
+impl<A, ES> fmt::Debug for CqrsFramework<A, ES>
+where
+    A: Aggregate,
+    ES: EventStore<A>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CqrsFramework")
+            .field("store", &std::any::type_name::<ES>())
+            .field("query_processors", &self.query_processors.len())
+            .finish()
+    }
 }
 
 impl<A, ES> CqrsFramework<A, ES>
@@ -56,8 +111,225 @@ where
         CqrsFramework {
             store,
             query_processors,
+            command_handler: Box::new(AggregateCommandHandler),
+            validators: Vec::new(),
+            observers: Vec::new(),
+            error_observers: Vec::new(),
+            inspectors: Vec::new(),
+            query_timeout: None,
+            rate_limiter: None,
+            max_events_per_aggregate: None,
+            subscribers: broadcast::channel(SUBSCRIBER_CAPACITY).0,
+            in_flight: Default::default(),
         }
     }
+    /// Creates new framework for dispatching commands using the provided elements.
+    /// Takes an `EventStore`, a vector of queries and a `CommandHandler` that will be used to
+    /// handle commands in place of `Aggregate::handle`.
+    ///
+    /// ```ignore
+    /// let store = MemStore::<Customer>::default();
+    /// let cqrs = CqrsFramework::new_with_command_handler(store, vec![], CustomerCommandHandler);
+    /// ```
+    pub fn new_with_command_handler<H>(
+        store: ES,
+        query_processors: Vec<Arc<dyn Query<A>>>,
+        command_handler: H,
+    ) -> CqrsFramework<A, ES>
+    where
+        H: CommandHandler<A> + 'static,
+    {
+        CqrsFramework {
+            command_handler: Box::new(command_handler),
+            ..CqrsFramework::new(store, query_processors)
+        }
+    }
+    /// Adds a `CommandValidator` that every command must pass before the aggregate is loaded.
+    /// Multiple validators may be added, they will be called in the order in which they were
+    /// added and the first failure is returned as an `AggregateError::ValidationError`.
+    ///
+    /// ```ignore
+    /// let store = MemStore::<Customer>::default();
+    /// let cqrs = CqrsFramework::new(store, vec![]).with_validator(CustomerCommandValidator);
+    /// ```
+    #[must_use]
+    pub fn with_validator<V>(mut self, validator: V) -> Self
+    where
+        V: CommandValidator<A> + 'static,
+    {
+        self.validators.push(Box::new(validator));
+        self
+    }
+    /// Adds an observer that is notified of each batch of committed events before they are
+    /// dispatched to any `Query`. Multiple observers may be added, they will be called in the
+    /// order in which they were added.
+    ///
+    /// This is useful for integration adapters, e.g., an outbox publisher, that must see committed
+    /// events before any downstream query.
+    ///
+    /// ```
+    /// # use cqrs_es::doc::MyAggregate;
+    /// use cqrs_es::CqrsFramework;
+    /// use cqrs_es::mem_store::MemStore;
+    ///
+    /// let store = MemStore::<MyAggregate>::default();
+    /// let cqrs = CqrsFramework::new(store, vec![])
+    ///     .with_observer(|events| println!("committed {} events", events.len()));
+    /// ```
+    #[must_use]
+    pub fn with_observer<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&[EventEnvelope<A>]) + Send + Sync + 'static,
+    {
+        self.observers.push(Box::new(f));
+        self
+    }
+    /// Adds an observer that is notified with the aggregate ID and error of each command that
+    /// fails, whether it is rejected by a validator or the aggregate, the aggregate does not
+    /// exist, the framework is shutting down or the events could not be committed. Multiple
+    /// observers may be added, they will be called in the order in which they were added.
+    ///
+    /// This provides a single place to log or report all command failures without modifying
+    /// every call site.
+    ///
+    /// ```
+    /// # use cqrs_es::doc::MyAggregate;
+    /// use cqrs_es::CqrsFramework;
+    /// use cqrs_es::mem_store::MemStore;
+    ///
+    /// let store = MemStore::<MyAggregate>::default();
+    /// let cqrs = CqrsFramework::new(store, vec![])
+    ///     .with_error_observer(|aggregate_id, err| println!("{} failed: {}", aggregate_id, err));
+    /// ```
+    #[must_use]
+    pub fn with_error_observer<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&str, &AggregateError) + Send + Sync + 'static,
+    {
+        self.error_observers.push(Box::new(f));
+        self
+    }
+    /// Adds an inspector that is called with the aggregate ID and the state of the aggregate
+    /// before and after each successful command, i.e., before the command is handled and after
+    /// the resulting events have been committed and applied. Multiple inspectors may be added,
+    /// they will be called in the order in which they were added.
+    ///
+    /// This is useful for profiling and for asserting exact state transitions in tests. Note that
+    /// while any inspectors are present each command copies the aggregate twice, using its
+    /// serialized form. If the aggregate cannot be copied a warning is logged and the inspectors
+    /// are not called for that command.
+    ///
+    /// ```
+    /// # use cqrs_es::doc::Customer;
+    /// use cqrs_es::CqrsFramework;
+    /// use cqrs_es::mem_store::MemStore;
+    ///
+    /// let store = MemStore::<Customer>::default();
+    /// let cqrs = CqrsFramework::new(store, vec![]).inspect(|aggregate_id, before, after| {
+    ///     println!("{}: '{}' -> '{}'", aggregate_id, before.name, after.name)
+    /// });
+    /// ```
+    #[must_use]
+    pub fn inspect<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&str, &A, &A) + Send + Sync + 'static,
+    {
+        self.inspectors.push(Box::new(f));
+        self
+    }
+    /// Limits the time that each `Query` may take to process a batch of committed events.
+    ///
+    /// A query that does not complete within the timeout is abandoned and a
+    /// `QueryError::Timeout` is logged, the command itself still succeeds. Without a timeout a
+    /// single slow query, e.g., one that updates a remote service, will delay every command.
+    ///
+    /// ```
+    /// # use cqrs_es::doc::MyAggregate;
+    /// use std::time::Duration;
+    /// use cqrs_es::CqrsFramework;
+    /// use cqrs_es::mem_store::MemStore;
+    ///
+    /// let store = MemStore::<MyAggregate>::default();
+    /// let cqrs = CqrsFramework::new(store, vec![])
+    ///     .with_query_timeout(Duration::from_millis(500));
+    /// ```
+    #[must_use]
+    pub fn with_query_timeout(mut self, duration: Duration) -> Self {
+        self.query_timeout = Some(duration);
+        self
+    }
+    /// Limits the rate at which commands may be applied to each aggregate instance, a command
+    /// exceeding the limit is rejected with a `UserError` with the code `"RATE_LIMIT_EXCEEDED"`
+    /// before the aggregate is loaded.
+    ///
+    /// ```
+    /// # use cqrs_es::doc::MyAggregate;
+    /// use std::time::Duration;
+    /// use cqrs_es::{CommandRateLimiter, CqrsFramework};
+    /// use cqrs_es::mem_store::MemStore;
+    ///
+    /// let store = MemStore::<MyAggregate>::default();
+    /// let cqrs = CqrsFramework::new(store, vec![])
+    ///     .with_rate_limiter(CommandRateLimiter::new(10, Duration::from_millis(100)));
+    /// ```
+    #[must_use]
+    pub fn with_rate_limiter(mut self, rate_limiter: CommandRateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+    /// Limits the number of events of each aggregate instance, protecting the store from runaway
+    /// aggregates. A command for an aggregate that has reached the limit is rejected with a
+    /// `UserError` with the code `"AGGREGATE_TOO_LARGE"` before the aggregate is loaded, such an
+    /// aggregate should be archived.
+    ///
+    /// The number of events is determined with `EventStore::last_event_version`.
+    ///
+    /// ```
+    /// # use cqrs_es::doc::MyAggregate;
+    /// use cqrs_es::CqrsFramework;
+    /// use cqrs_es::mem_store::MemStore;
+    ///
+    /// let store = MemStore::<MyAggregate>::default();
+    /// let cqrs = CqrsFramework::new(store, vec![]).with_max_events_per_aggregate(100_000);
+    /// ```
+    #[must_use]
+    pub fn with_max_events_per_aggregate(mut self, max: usize) -> Self {
+        self.max_events_per_aggregate = Some(max);
+        self
+    }
+    /// Subscribes to all events committed by this framework after the call to `subscribe`.
+    ///
+    /// Unlike a `Query`, a subscription may be created at any time after the framework has been
+    /// constructed, e.g., by a background task that needs real-time notification of events.
+    /// Subscribers that fall more than 1024 events behind will receive a `RecvError::Lagged` and
+    /// miss the oldest events.
+    ///
+    /// ```ignore
+    /// let mut receiver = cqrs.subscribe();
+    /// tokio::spawn(async move {
+    ///     while let Ok(event) = receiver.recv().await {
+    ///         println!("committed {}", event);
+    ///     }
+    /// });
+    /// ```
+    pub fn subscribe(&self) -> broadcast::Receiver<EventEnvelope<A>> {
+        self.subscribers.subscribe()
+    }
+    /// Signals that the framework is shutting down, e.g., on receiving a SIGTERM, and returns a
+    /// handle that may be awaited until all in-flight commands have completed.
+    ///
+    /// Any command executed after shutdown has been signaled is rejected with an
+    /// `AggregateError::TechnicalError`.
+    ///
+    /// ```ignore
+    /// let handle = cqrs.shutdown_signal();
+    /// if tokio::time::timeout(Duration::from_secs(10), handle).await.is_err() {
+    ///     println!("commands still in flight after 10 seconds");
+    /// }
+    /// ```
+    pub fn shutdown_signal(&self) -> ShutdownHandle {
+        self.in_flight.shutdown()
+    }
     /// This applies a command to an aggregate. Executing a command
     /// in this way is the only way to make any change to
     /// the state of an aggregate.
@@ -81,6 +353,179 @@ where
             .await
     }
 
+    /// This applies a command to an aggregate in the same way as `execute`, but distinguishes
+    /// the ways in which the command may fail. A `CommandDispatchError::Domain` indicates that the
+    /// command was rejected, a `CommandDispatchError::ShuttingDown` that the framework no longer
+    /// accepts commands, a `CommandDispatchError::Store` that the event store failed, and a
+    /// `CommandDispatchError::Query` that the events were committed but not every query
+    /// processed them.
+    ///
+    /// ```ignore
+    /// match cqrs.try_execute("agg-id-F39A0C", MyCommands::DoSomething).await {
+    ///     Ok(()) => {}
+    ///     Err(CommandDispatchError::Domain(err)) => println!("rejected: {}", err),
+    ///     Err(CommandDispatchError::Query(errors)) => println!("{} queries are stale", errors.len()),
+    ///     Err(err) => return Err(err.into()),
+    /// }
+    /// ```
+    pub async fn try_execute(
+        &self,
+        aggregate_id: &str,
+        command: A::Command,
+    ) -> Result<(), CommandDispatchError> {
+        let (_, query_errors) = self
+            .dispatch_command(aggregate_id, command, CommandContext::default())
+            .await?;
+        if !query_errors.is_empty() {
+            return Err(CommandDispatchError::Query(query_errors));
+        }
+        Ok(())
+    }
+
+    /// This applies a command to an aggregate that must already exist, i.e., have at least one
+    /// previously committed event.
+    ///
+    /// If no events are found for the aggregate an `AggregateError::NotFound` is returned and the
+    /// command is never handled, otherwise this behaves identically to `execute`.
+    ///
+    /// ```ignore
+    /// let command = MyCommands::DoSomething;
+    ///
+    /// match cqrs.execute_if_exists("agg-id-F39A0C", command).await {
+    ///     Err(AggregateError::NotFound(aggregate_id)) => println!("no aggregate {}", aggregate_id),
+    ///     result => result?,
+    /// }
+    /// ```
+    pub async fn execute_if_exists(
+        &self,
+        aggregate_id: &str,
+        command: A::Command,
+    ) -> Result<(), AggregateError> {
+        if self.store.load(aggregate_id).await.is_empty() {
+            let err = AggregateError::NotFound(aggregate_id.to_string());
+            self.notify_error_observers(aggregate_id, &err);
+            return Err(err);
+        }
+        self.execute(aggregate_id, command).await
+    }
+
+    /// This applies each command to its aggregate instance concurrently, e.g., when importing a
+    /// batch of records. The results are returned in the same order as the commands, a failure
+    /// of one command does not prevent the remaining commands from being applied.
+    ///
+    /// Commands directed at the same aggregate instance may conflict with one another, these
+    /// should be executed in sequence instead.
+    ///
+    /// ```ignore
+    /// let results = cqrs
+    ///     .execute_many(vec![
+    ///         ("agg-id-F39A0C".to_string(), MyCommands::DoSomething),
+    ///         ("agg-id-B72D11".to_string(), MyCommands::DoSomething),
+    ///     ])
+    ///     .await;
+    /// ```
+    pub async fn execute_many(
+        &self,
+        commands: Vec<(String, A::Command)>,
+    ) -> Vec<Result<(), AggregateError>> {
+        let executions = commands
+            .into_iter()
+            .map(
+                |(aggregate_id, command)| async move { self.execute(&aggregate_id, command).await },
+            );
+        join_all(executions).await
+    }
+
+    /// This applies a command to a new aggregate instance, generating a random UUID to use as its
+    /// aggregate ID. The generated ID is returned if the command succeeds.
+    ///
+    /// ```ignore
+    /// let command = MyCommands::DoSomething;
+    ///
+    /// let aggregate_id = cqrs.execute_for_new_aggregate(command).await?;
+    /// ```
+    pub async fn execute_for_new_aggregate(
+        &self,
+        command: A::Command,
+    ) -> Result<String, AggregateError> {
+        let aggregate_id = Uuid::new_v4().to_string();
+        self.execute(&aggregate_id, command).await?;
+        Ok(aggregate_id)
+    }
+
+    /// Loads and returns the current state of an aggregate instance without handling any command.
+    /// This is useful for inspection and verification where the aggregate must not be changed.
+    ///
+    /// The aggregate is loaded with `EventStore::load_aggregate`, the returned aggregate is a copy
+    /// of the loaded aggregate made by serializing it. Use `execute_read_only` to read the loaded
+    /// aggregate without copying it.
+    ///
+    /// If no events are found for the aggregate an `AggregateError::NotFound` is returned.
+    ///
+    /// ```ignore
+    /// let aggregate = cqrs.load_and_return_aggregate("agg-id-F39A0C").await?;
+    /// ```
+    pub async fn load_and_return_aggregate(&self, aggregate_id: &str) -> Result<A, AggregateError> {
+        self.execute_read_only(aggregate_id, copy_aggregate).await?
+    }
+
+    /// Loads the current state of an aggregate instance and returns the result of the provided
+    /// function applied to it, without handling any command. This provides a consistent read of
+    /// the aggregate when a query may not yet reflect the latest events. The aggregate is loaded
+    /// with `EventStore::load_aggregate`, in the same way as when handling a command.
+    ///
+    /// If no events are found for the aggregate an `AggregateError::NotFound` is returned and the
+    /// function is not called.
+    ///
+    /// ```ignore
+    /// let email = cqrs
+    ///     .execute_read_only("agg-id-F39A0C", |customer| customer.email.clone())
+    ///     .await?;
+    /// ```
+    pub async fn execute_read_only<R, F>(
+        &self,
+        aggregate_id: &str,
+        f: F,
+    ) -> Result<R, AggregateError>
+    where
+        F: FnOnce(&A) -> R,
+    {
+        let aggregate_context = self.store.load_aggregate(aggregate_id).await;
+        let found = match aggregate_context.current_sequence() {
+            Some(sequence) => sequence > 0,
+            None => self.store.last_event_version(aggregate_id).await.is_some(),
+        };
+        if !found {
+            return Err(AggregateError::NotFound(aggregate_id.to_string()));
+        }
+        Ok(f(aggregate_context.aggregate()))
+    }
+
+    /// This applies a command packaged in a `CommandEnvelope` to an aggregate.
+    ///
+    /// Any command ID is added to the metadata under the
+    /// [`COMMAND_ID_METADATA_KEY`](constant.COMMAND_ID_METADATA_KEY.html) and so is recorded with
+    /// every committed event, allowing a store to detect duplicate submissions of the same command.
+    /// A command that the store reports as a
+    /// [duplicate](trait.EventStore.html#method.is_duplicate_command) succeeds without being
+    /// handled, and no events are dispatched to the queries.
+    ///
+    /// ```ignore
+    /// let envelope = CommandEnvelope::new(MyCommands::DoSomething)
+    ///     .with_command_id("8A3B9C6D".to_string());
+    ///
+    /// cqrs.execute_envelope("agg-id-F39A0C", envelope).await;
+    /// ```
+    pub async fn execute_envelope(
+        &self,
+        aggregate_id: &str,
+        envelope: CommandEnvelope<A>,
+    ) -> Result<(), AggregateError> {
+        let (command, context) = envelope.into_parts();
+        self.execute_with_context(aggregate_id, command, context)
+            .await
+    }
+
     /// This applies a command to an aggregate along with associated metadata. Executing a command
     /// in this way to make any change to the state of an aggregate.
     ///
@@ -109,17 +554,279 @@ where
         command: A::Command,
         metadata: HashMap<String, String>,
     ) -> Result<(), AggregateError> {
-        let aggregate_context = self.store.load_aggregate(aggregate_id).await;
-        let aggregate = aggregate_context.aggregate();
-        let resultant_events = aggregate.handle(command)?;
+        let context = CommandContext::default().with_metadata(metadata);
+        self.execute_with_context(aggregate_id, command, context)
+            .await
+    }
+
+    /// This applies a command to an aggregate in the same way as `execute`, but abandons the
+    /// command if it has not completed within the timeout, returning an
+    /// `AggregateError::TechnicalError("timeout")`. This bounds the latency of a command when the
+    /// event store or a query may hang.
+    ///
+    /// Note that a command that times out while its events are being committed may still have
+    /// been committed, and the events may not have been dispatched to every query.
+    ///
+    /// ```ignore
+    /// cqrs.execute_with_timeout("agg-id-F39A0C", MyCommands::DoSomething, Duration::from_secs(5))
+    ///     .await?;
+    /// ```
+    pub async fn execute_with_timeout(
+        &self,
+        aggregate_id: &str,
+        command: A::Command,
+        timeout: Duration,
+    ) -> Result<(), AggregateError> {
+        let execution = self.execute_with_metadata(aggregate_id, command, HashMap::new());
+        match tokio::time::timeout(timeout, execution).await {
+            Ok(result) => result,
+            Err(_) => {
+                let err = AggregateError::TechnicalError("timeout".to_string());
+                self.notify_error_observers(aggregate_id, &err);
+                Err(err)
+            }
+        }
+    }
+
+    /// This applies a command to an aggregate along with a `CommandContext` holding all
+    /// cross-cutting information for the command.
+    ///
+    /// The metadata of the context, along with any command ID, idempotency key and correlation ID,
+    /// will be attached to any produced events. The identifiers are recorded under the
+    /// [`COMMAND_ID_METADATA_KEY`](constant.COMMAND_ID_METADATA_KEY.html),
+    /// [`IDEMPOTENCY_KEY_METADATA_KEY`](constant.IDEMPOTENCY_KEY_METADATA_KEY.html) and
+    /// [`CORRELATION_ID_METADATA_KEY`](constant.CORRELATION_ID_METADATA_KEY.html) respectively.
+    ///
+    /// An error while processing will result in no events committed and
+    /// an AggregateError being returned.
+    ///
+    /// If successful the events produced will be applied to the configured `QueryProcessor`s.
+    ///
+    /// ```ignore
+    /// let command = MyCommands::DoSomething;
+    /// let context = CommandContext::default()
+    ///     .with_metadata_entry("time", chrono::Utc::now().to_rfc3339())
+    ///     .with_command_id("8A3B9C6D")
+    ///     .with_correlation_id("request-5F1E");
+    ///
+    /// cqrs.execute_with_context("agg-id-F39A0C", command, context).await;
+    /// ```
+    pub async fn execute_with_context(
+        &self,
+        aggregate_id: &str,
+        command: A::Command,
+        context: CommandContext,
+    ) -> Result<(), AggregateError> {
+        self.process_command(aggregate_id, command, context).await?;
+        Ok(())
+    }
+
+    /// This applies a command to an aggregate and returns the resulting version of the aggregate,
+    /// i.e., the sequence number of the last committed event. This is useful for clients that
+    /// track the version, e.g., for ETag generation, without a separate read.
+    ///
+    /// If the command produces no events the current version is returned, this will be `0` for an
+    /// aggregate with no events.
+    ///
+    /// ```ignore
+    /// let command = MyCommands::DoSomething;
+    ///
+    /// let version = cqrs.execute_and_return_version("agg-id-F39A0C", command).await?;
+    /// ```
+    pub async fn execute_and_return_version(
+        &self,
+        aggregate_id: &str,
+        command: A::Command,
+    ) -> Result<usize, AggregateError> {
         let committed_events = self
-            .store
-            .commit(resultant_events, aggregate_context, metadata)
+            .process_command(aggregate_id, command, CommandContext::default())
+            .await?;
+        let version = match committed_events.last() {
+            Some(event) => Some(event.sequence),
+            None => self.store.last_event_version(aggregate_id).await,
+        };
+        Ok(version.unwrap_or(0))
+    }
+
+    async fn process_command(
+        &self,
+        aggregate_id: &str,
+        command: A::Command,
+        context: CommandContext,
+    ) -> Result<Vec<EventEnvelope<A>>, AggregateError> {
+        let (committed_events, _) = self
+            .dispatch_command(aggregate_id, command, context)
             .await?;
+        Ok(committed_events)
+    }
+    async fn dispatch_command(
+        &self,
+        aggregate_id: &str,
+        command: A::Command,
+        context: CommandContext,
+    ) -> Result<(Vec<EventEnvelope<A>>, Vec<QueryError>), CommandDispatchError> {
+        let _in_flight = match self.in_flight.start() {
+            Ok(in_flight) => in_flight,
+            Err(err) => {
+                self.notify_error_observers(aggregate_id, &err);
+                return Err(CommandDispatchError::ShuttingDown(err));
+            }
+        };
+        let committed_events = match self.commit_command(aggregate_id, command, context).await {
+            Ok(Some(committed_events)) => committed_events,
+            // a duplicate command was committed previously, its events have been dispatched
+            Ok(None) => return Ok((Vec::new(), Vec::new())),
+            Err(err) => {
+                match &err {
+                    CommandDispatchError::Domain(err)
+                    | CommandDispatchError::ShuttingDown(err)
+                    | CommandDispatchError::Store(err) => {
+                        self.notify_error_observers(aggregate_id, err)
+                    }
+                    CommandDispatchError::Query(_) => {}
+                }
+                return Err(err);
+            }
+        };
+        for observer in &self.observers {
+            observer(committed_events.as_slice());
+        }
+        if self.subscribers.receiver_count() > 0 {
+            for event in &committed_events {
+                // an error here only indicates that all subscribers have since been dropped
+                let _ = self.subscribers.send(event.clone());
+            }
+        }
+        let mut query_errors = Vec::new();
         for processor in &self.query_processors {
+            if !processor.subscribes_to(aggregate_id) {
+                continue;
+            }
             let dispatch_events = committed_events.as_slice();
-            processor.dispatch(aggregate_id, dispatch_events).await;
+            let dispatch = processor.dispatch(aggregate_id, dispatch_events);
+            match self.query_timeout {
+                None => dispatch.await,
+                Some(timeout) => {
+                    if tokio::time::timeout(timeout, dispatch).await.is_err() {
+                        let err = QueryError::Timeout(timeout);
+                        warn!(
+                            aggregate_type = A::aggregate_type(),
+                            aggregate_id,
+                            query = processor.name(),
+                            error = %err,
+                            "query dispatch abandoned"
+                        );
+                        query_errors.push(err);
+                    }
+                }
+            }
         }
-        Ok(())
+        Ok((committed_events, query_errors))
     }
+    fn notify_error_observers(&self, aggregate_id: &str, err: &AggregateError) {
+        for error_observer in &self.error_observers {
+            error_observer(aggregate_id, err);
+        }
+    }
+    // Returns `None` if the command is a duplicate of a previously committed command.
+    async fn commit_command(
+        &self,
+        aggregate_id: &str,
+        command: A::Command,
+        context: CommandContext,
+    ) -> Result<Option<Vec<EventEnvelope<A>>>, CommandDispatchError> {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter
+                .check(aggregate_id)
+                .map_err(CommandDispatchError::Domain)?;
+        }
+        for validator in &self.validators {
+            validator
+                .validate(&command)
+                .map_err(|err| CommandDispatchError::Domain(err.into()))?;
+        }
+        let metadata = context.into_metadata();
+        if let Some(command_id) = metadata.get(COMMAND_ID_METADATA_KEY) {
+            if self
+                .store
+                .is_duplicate_command(aggregate_id, command_id)
+                .await
+            {
+                return Ok(None);
+            }
+        }
+        if let Some(max) = self.max_events_per_aggregate {
+            let event_count = self.store.last_event_version(aggregate_id).await;
+            if event_count.unwrap_or(0) >= max {
+                return Err(CommandDispatchError::Domain(AggregateError::new_with_code(
+                    "aggregate too large, please archive",
+                    "AGGREGATE_TOO_LARGE",
+                )));
+            }
+        }
+        let aggregate_context = self.store.load_aggregate(aggregate_id).await;
+        let is_new = aggregate_context
+            .current_sequence()
+            .map(|sequence| sequence == 0);
+        let aggregate = aggregate_context.aggregate();
+        let resultant_events = self
+            .command_handler
+            .handle(aggregate, command)
+            .map_err(CommandDispatchError::Domain)?;
+        let inspected_states = if self.inspectors.is_empty() {
+            None
+        } else {
+            // inspection is diagnostic only, a failed copy does not fail the command
+            let copies = copy_aggregate(aggregate)
+                .and_then(|before| Ok((before, copy_aggregate(aggregate)?)));
+            match copies {
+                Ok(states) => Some(states),
+                Err(err) => {
+                    warn!(
+                        aggregate_type = A::aggregate_type(),
+                        aggregate_id,
+                        error = %err,
+                        "aggregate could not be copied, state inspectors skipped"
+                    );
+                    None
+                }
+            }
+        };
+        let committed_events = match self
+            .store
+            .commit(resultant_events, aggregate_context, metadata)
+            .await
+        {
+            Ok(committed_events) => committed_events,
+            // the command was committed concurrently since the check above
+            Err(AggregateError::DuplicateCommand(_)) => return Ok(None),
+            Err(err) => return Err(CommandDispatchError::Store(err)),
+        };
+        // for a context that does not provide its sequence, rely on the committed sequence
+        let is_new = is_new.unwrap_or_else(|| {
+            committed_events.first().map(|envelope| envelope.sequence) == Some(1)
+        });
+        if is_new && !committed_events.is_empty() {
+            let mut created = A::default();
+            created.set_aggregate_id(aggregate_id);
+            for envelope in &committed_events {
+                created.apply(envelope.payload.clone());
+            }
+            created.on_create();
+        }
+        if let Some((before, mut after)) = inspected_states {
+            for envelope in &committed_events {
+                after.apply(envelope.payload.clone());
+            }
+            for inspector in &self.inspectors {
+                inspector(aggregate_id, &before, &after);
+            }
+        }
+        Ok(Some(committed_events))
+    }
+}
+
+// Aggregates are not required to be `Clone`, a copy is made using the serialized form.
+fn copy_aggregate<A: Aggregate>(aggregate: &A) -> Result<A, AggregateError> {
+    Ok(serde_json::from_value(serde_json::to_value(aggregate)?)?)
 }
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+
+use crate::command::COMMAND_ID_METADATA_KEY;
+use crate::event::EventEnvelope;
+use crate::{
+    Aggregate, AggregateContext, AggregateError, CountableEventStore, EventEnvelopeStream,
+    EventStore,
+};
+
+/// An event store wrapper that makes commits idempotent for stores that do not detect duplicate
+/// commands themselves.
+///
+/// A commit providing a command ID in the metadata under the
+/// [`COMMAND_ID_METADATA_KEY`](constant.COMMAND_ID_METADATA_KEY.html) is committed only once for
+/// each aggregate instance, a repeated commit with the same command ID is rejected with an
+/// `AggregateError::DuplicateCommand` which the `CqrsFramework` treats as success. A repeated
+/// commit made while the first is still in progress is rejected with an
+/// `AggregateError::AggregateConflict`, since the first may yet fail. Commits without a command
+/// ID are always committed.
+///
+/// The aggregate instance is identified by
+/// [`AggregateContext::aggregate_id`](trait.AggregateContext.html#method.aggregate_id), for a
+/// context that does not provide it command IDs are deduplicated across all aggregate instances.
+/// The command IDs that have been seen are held in memory and are lost when the store is dropped.
+///
+/// ```
+/// # use cqrs_es::doc::MyAggregate;
+/// use cqrs_es::{CqrsFramework, DeduplicatingEventStore};
+/// use cqrs_es::mem_store::MemStore;
+///
+/// let store = DeduplicatingEventStore::new(MemStore::<MyAggregate>::default());
+/// let cqrs = CqrsFramework::new(store, vec![]);
+/// ```
+pub struct DeduplicatingEventStore<A, ES>
+where
+    A: Aggregate,
+{
+    store: ES,
+    commands: Mutex<HashMap<(String, String), CommandState>>,
+    _phantom: PhantomData<A>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CommandState {
+    Pending,
+    Committed,
+}
+
+impl<A, ES> DeduplicatingEventStore<A, ES>
+where
+    A: Aggregate,
+{
+    /// Wraps the provided event store, ignoring duplicate commits of the same command.
+    pub fn new(store: ES) -> Self {
+        DeduplicatingEventStore {
+            store,
+            commands: Default::default(),
+            _phantom: PhantomData,
+        }
+    }
+    fn command_state(&self, command_key: &(String, String)) -> Option<CommandState> {
+        // uninteresting unwrap: the lock is never held across a panic
+        self.commands.lock().unwrap().get(command_key).copied()
+    }
+}
+
+#[async_trait]
+impl<A, ES> EventStore<A> for DeduplicatingEventStore<A, ES>
+where
+    A: Aggregate + 'static,
+    ES: EventStore<A> + 'static,
+{
+    type AC = ES::AC;
+
+    async fn load(&self, aggregate_id: &str) -> Vec<EventEnvelope<A>> {
+        self.store.load(aggregate_id).await
+    }
+
+    fn load_stream<'a>(&'a self, aggregate_id: &'a str) -> EventEnvelopeStream<'a, A>
+    where
+        A: 'a,
+    {
+        self.store.load_stream(aggregate_id)
+    }
+
+    async fn load_aggregate(&self, aggregate_id: &str) -> Self::AC {
+        self.store.load_aggregate(aggregate_id).await
+    }
+
+    async fn last_event_version(&self, aggregate_id: &str) -> Option<usize> {
+        self.store.last_event_version(aggregate_id).await
+    }
+
+    async fn is_duplicate_command(&self, aggregate_id: &str, command_id: &str) -> bool {
+        let command_key = (aggregate_id.to_string(), command_id.to_string());
+        self.command_state(&command_key) == Some(CommandState::Committed)
+            || self
+                .store
+                .is_duplicate_command(aggregate_id, command_id)
+                .await
+    }
+
+    async fn commit(
+        &self,
+        events: Vec<A::Event>,
+        context: Self::AC,
+        metadata: HashMap<String, String>,
+    ) -> Result<Vec<EventEnvelope<A>>, AggregateError> {
+        let command_id = match metadata.get(COMMAND_ID_METADATA_KEY) {
+            Some(command_id) => command_id.clone(),
+            None => return self.store.commit(events, context, metadata).await,
+        };
+        let aggregate_id = context.aggregate_id().unwrap_or_default().to_string();
+        let command_key = (aggregate_id, command_id);
+        {
+            // the command is reserved, the lock is not held while committing to the store
+            // uninteresting unwrap: the lock is never held across a panic
+            let mut commands = self.commands.lock().unwrap();
+            match commands.get(&command_key) {
+                Some(CommandState::Committed) => {
+                    return Err(AggregateError::DuplicateCommand(command_key.1));
+                }
+                Some(CommandState::Pending) => return Err(AggregateError::AggregateConflict),
+                None => {
+                    commands.insert(command_key.clone(), CommandState::Pending);
+                }
+            }
+        }
+        let result = self.store.commit(events, context, metadata).await;
+        // uninteresting unwrap: the lock is never held across a panic
+        let mut commands = self.commands.lock().unwrap();
+        match &result {
+            Ok(_) | Err(AggregateError::DuplicateCommand(_)) => {
+                commands.insert(command_key, CommandState::Committed);
+            }
+            Err(_) => {
+                commands.remove(&command_key);
+            }
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl<A, ES> CountableEventStore<A> for DeduplicatingEventStore<A, ES>
+where
+    A: Aggregate + 'static,
+    ES: CountableEventStore<A> + 'static,
+{
+    async fn count_events(&self) -> usize {
+        self.store.count_events().await
+    }
+}
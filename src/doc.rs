@@ -78,6 +78,13 @@ impl Aggregate for Customer {
             }
         }
     }
+    fn aggregate_id(&self) -> Option<&str> {
+        Some(&self.customer_id)
+    }
+
+    fn set_aggregate_id(&mut self, aggregate_id: &str) {
+        self.customer_id = aggregate_id.to_string();
+    }
 }
 
 impl Default for Customer {
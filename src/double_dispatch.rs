@@ -0,0 +1,87 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tracing::warn;
+
+use crate::event::EventEnvelope;
+use crate::{Aggregate, CqrsFramework, EventStore, Query};
+
+/// The mapping used by a `DoubleDispatch` to produce a command for the target aggregate from an
+/// event of the source aggregate, returning the target aggregate ID along with the command or
+/// `None` if the event should be ignored.
+pub type DoubleDispatchMapping<S, T> =
+    fn(&EventEnvelope<S>) -> Option<(String, <T as Aggregate>::Command)>;
+
+/// A `Query` that issues commands to a sibling aggregate within the same bounded context in
+/// response to committed events, e.g., reserving inventory when an order is placed.
+///
+/// Register the `DoubleDispatch` as a query on the framework of the source aggregate, each
+/// committed event is passed to the mapping and any resulting command is executed against the
+/// framework of the target aggregate. A failure of the target command is logged, the source
+/// events will have already been committed.
+///
+/// ```ignore
+/// fn reserve_inventory(event: &EventEnvelope<Order>) -> Option<(String, InventoryCommand)> {
+///     match &event.payload {
+///         OrderEvent::OrderPlaced(placed) => Some((
+///             placed.sku.clone(),
+///             InventoryCommand::Reserve { quantity: placed.quantity },
+///         )),
+///         _ => None,
+///     }
+/// }
+///
+/// let inventory_cqrs = Arc::new(CqrsFramework::new(inventory_store, vec![]));
+/// let double_dispatch = DoubleDispatch::new(inventory_cqrs, reserve_inventory);
+/// let order_cqrs = CqrsFramework::new(order_store, vec![Arc::new(double_dispatch)]);
+/// ```
+pub struct DoubleDispatch<S, T, ES>
+where
+    S: Aggregate,
+    T: Aggregate,
+    ES: EventStore<T>,
+{
+    target: Arc<CqrsFramework<T, ES>>,
+    mapping: DoubleDispatchMapping<S, T>,
+}
+
+impl<S, T, ES> DoubleDispatch<S, T, ES>
+where
+    S: Aggregate,
+    T: Aggregate,
+    ES: EventStore<T>,
+{
+    /// Creates a new `DoubleDispatch` that executes the commands produced by `mapping` on the
+    /// target framework.
+    pub fn new(target: Arc<CqrsFramework<T, ES>>, mapping: DoubleDispatchMapping<S, T>) -> Self {
+        DoubleDispatch { target, mapping }
+    }
+}
+
+#[async_trait]
+impl<S, T, ES> Query<S> for DoubleDispatch<S, T, ES>
+where
+    S: Aggregate,
+    T: Aggregate,
+    T::Command: Send,
+    ES: EventStore<T>,
+{
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<S>]) {
+        for event in events {
+            let (target_id, command) = match (self.mapping)(event) {
+                Some(target_command) => target_command,
+                None => continue,
+            };
+            if let Err(err) = self.target.execute(&target_id, command).await {
+                warn!(
+                    source_type = S::aggregate_type(),
+                    source_id = aggregate_id,
+                    target_type = T::aggregate_type(),
+                    target_id = target_id.as_str(),
+                    error = %err,
+                    "double dispatch command failed"
+                );
+            }
+        }
+    }
+}
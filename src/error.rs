@@ -2,9 +2,32 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::error;
 use std::fmt;
+use std::time::Duration;
 
 /// The base error for the framework.
-#[derive(Debug, PartialEq)]
+///
+/// This error implements `Clone` so that it may be shared, e.g., when reporting the same failure
+/// for a batch of commands, and `Eq` so that it may be compared with `assert_eq!` in tests. Any
+/// payload added to a variant must therefore also implement `Clone` and `Eq`.
+///
+/// New variants may be added in future releases, any `match` on this error outside of this crate
+/// must include a wildcard arm.
+///
+/// The error may be serialized, e.g., as the body of an API response, and deserialized again,
+/// e.g., when propagating an error between services. The serialized form holds the `variant`, a
+/// `message` and, where available, the `code` and additional `fields` of the error.
+///
+/// ```
+/// # use cqrs_es::AggregateError;
+/// let error = AggregateError::new_with_code("user already exists", "USER_EXISTS");
+/// assert_eq!(
+///     r#"{"variant":"UserError","message":"user already exists","code":"USER_EXISTS"}"#,
+///     serde_json::to_string(&error).unwrap()
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "AggregateErrorBody", try_from = "AggregateErrorBody")]
+#[non_exhaustive]
 pub enum AggregateError {
     /// This is the error returned when a user violates a business rule. The information within
     /// the `UserErrorPayload` should be used to inform the user of their error.
@@ -29,12 +52,45 @@ pub enum AggregateError {
     ///
     /// In a production system this may indicate a serious error and should be investigated.
     TechnicalError(String),
+    /// The command was directed at an aggregate instance that has no previously committed events.
+    /// The payload holds the ID of the requested aggregate instance.
+    ///
+    /// ### Handling
+    /// In a Restful application this should translate to a 404 response status.
+    NotFound(String),
+    /// A command failed schema-level validation by a
+    /// [`CommandValidator`](trait.CommandValidator.html) and was never handled by the aggregate.
+    ///
+    /// ### Handling
+    /// In a Restful application this should translate to a 400 response status.
+    ValidationError(ValidationError),
+    /// The command has already been committed for the aggregate instance, as identified by the
+    /// command ID held in the payload. Event stores that record command IDs return this rather
+    /// than committing the events of a command a second time.
+    ///
+    /// ### Handling
+    /// The `CqrsFramework` treats this as the successful completion of a retried command, any
+    /// other caller should do the same rather than retrying again.
+    ///
+    /// In a Restful application this usually translates to a 409 response status.
+    DuplicateCommand(String),
+    /// An error that has been annotated with additional context describing where or why it
+    /// occurred, see [`AggregateError::context`](enum.AggregateError.html#method.context).
+    ///
+    /// ### Handling
+    /// This should be handled identically to the `inner` error.
+    ContextualError {
+        /// The original error.
+        inner: Box<AggregateError>,
+        /// A description of the context in which the error occurred.
+        context: String,
+    },
 }
 
 /// Payload for an `AggregateError::UserError`, somewhat modeled on the errors produced by the
 /// [`validator`](https://github.com/Keats/validator) package. This payload implements `Serialize`
 /// with the intention of allowing the user to return this object as the response payload.
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct UserErrorPayload {
     /// An optional code to indicate the a user-defined error.
     pub code: Option<String>,
@@ -44,6 +100,269 @@ pub struct UserErrorPayload {
     pub params: Option<HashMap<String, String>>,
 }
 
+/// The error returned by a `CommandValidator` when a command fails validation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationError {
+    /// The command field that failed validation, if the failure is specific to a single field.
+    pub field: Option<String>,
+    /// A description of the failure, meant to be returned to the user.
+    pub message: String,
+}
+
+impl ValidationError {
+    /// Creates a new error with the provided description.
+    ///
+    /// ```
+    /// # use cqrs_es::ValidationError;
+    /// let error = ValidationError::new("at least one item is required");
+    /// ```
+    pub fn new(message: &str) -> Self {
+        ValidationError {
+            field: None,
+            message: message.to_string(),
+        }
+    }
+    /// Creates a new error for a single field of the command.
+    ///
+    /// ```
+    /// # use cqrs_es::ValidationError;
+    /// let error = ValidationError::for_field("name", "name must not be empty");
+    /// assert_eq!("invalid name: name must not be empty", error.to_string());
+    /// ```
+    pub fn for_field(field: &str, message: &str) -> Self {
+        ValidationError {
+            field: Some(field.to_string()),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl error::Error for ValidationError {}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.field {
+            Some(field) => write!(f, "invalid {}: {}", field, self.message),
+            None => write!(f, "invalid command: {}", self.message),
+        }
+    }
+}
+
+impl From<ValidationError> for AggregateError {
+    fn from(err: ValidationError) -> Self {
+        AggregateError::ValidationError(err)
+    }
+}
+
+/// The error produced when dispatching events to a `Query` fails. These errors are logged by the
+/// `CqrsFramework` and do not fail the command that produced the events.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum QueryError {
+    /// The query did not complete within the configured timeout.
+    Timeout(Duration),
+}
+
+impl error::Error for QueryError {}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::Timeout(timeout) => write!(f, "query timed out after {:?}", timeout),
+        }
+    }
+}
+
+/// The error returned by
+/// [`CqrsFramework::try_execute`](struct.CqrsFramework.html#method.try_execute), separating the
+/// rejection of a command by the domain from failures of the event store or of queries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CommandDispatchError {
+    /// The command was rejected by a `CommandRateLimiter`, a `CommandValidator`, the limit on the
+    /// number of events of an aggregate or by the aggregate itself, no events were committed.
+    Domain(AggregateError),
+    /// The command was not accepted because the framework is shutting down, it was never handled
+    /// and no events were committed. The command may be retried against another instance.
+    ShuttingDown(AggregateError),
+    /// The events produced by the command could not be committed to the event store.
+    Store(AggregateError),
+    /// The events were committed but one or more queries failed to process them.
+    Query(Vec<QueryError>),
+}
+
+impl error::Error for CommandDispatchError {}
+
+impl fmt::Display for CommandDispatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandDispatchError::Domain(err) => write!(f, "command rejected: {}", err),
+            CommandDispatchError::ShuttingDown(err) => write!(f, "command not accepted: {}", err),
+            CommandDispatchError::Store(err) => write!(f, "event store failure: {}", err),
+            CommandDispatchError::Query(errors) => {
+                write!(f, "{} queries failed", errors.len())?;
+                for err in errors {
+                    write!(f, ", {}", err)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl From<CommandDispatchError> for AggregateError {
+    fn from(err: CommandDispatchError) -> Self {
+        match err {
+            CommandDispatchError::Domain(err)
+            | CommandDispatchError::ShuttingDown(err)
+            | CommandDispatchError::Store(err) => err,
+            CommandDispatchError::Query(_) => AggregateError::TechnicalError(err.to_string()),
+        }
+    }
+}
+
+/// The error returned when an `EventStoreHealthCheck` fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthCheckError {
+    /// The time spent on the health check before the failure was detected.
+    pub elapsed: Duration,
+    /// An optional description of the failure.
+    pub description: Option<String>,
+}
+
+impl error::Error for HealthCheckError {}
+
+impl fmt::Display for HealthCheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.description {
+            Some(description) => write!(
+                f,
+                "health check failed after {:?}: {}",
+                self.elapsed, description
+            ),
+            None => write!(f, "health check failed after {:?}", self.elapsed),
+        }
+    }
+}
+
+// The serialized form of an `AggregateError`.
+#[derive(Serialize, Deserialize)]
+struct AggregateErrorBody {
+    variant: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fields: Option<HashMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    inner: Option<Box<AggregateErrorBody>>,
+}
+
+const NOT_FOUND_AGGREGATE_ID_FIELD: &str = "aggregate_id";
+const VALIDATION_FIELD_FIELD: &str = "field";
+const DUPLICATE_COMMAND_ID_FIELD: &str = "command_id";
+
+impl From<AggregateError> for AggregateErrorBody {
+    fn from(err: AggregateError) -> Self {
+        let message = Some(err.to_string());
+        let mut body = AggregateErrorBody {
+            variant: String::new(),
+            message,
+            code: None,
+            fields: None,
+            inner: None,
+        };
+        match err {
+            AggregateError::UserError(payload) => {
+                body.variant = "UserError".to_string();
+                body.message = payload.message;
+                body.code = payload.code;
+                body.fields = payload.params;
+            }
+            AggregateError::AggregateConflict => {
+                body.variant = "AggregateConflict".to_string();
+            }
+            AggregateError::TechnicalError(_) => {
+                body.variant = "TechnicalError".to_string();
+            }
+            AggregateError::NotFound(aggregate_id) => {
+                body.variant = "NotFound".to_string();
+                let mut fields = HashMap::new();
+                fields.insert(NOT_FOUND_AGGREGATE_ID_FIELD.to_string(), aggregate_id);
+                body.fields = Some(fields);
+            }
+            AggregateError::ValidationError(err) => {
+                body.variant = "ValidationError".to_string();
+                body.message = Some(err.message);
+                body.fields = err.field.map(|field| {
+                    let mut fields = HashMap::new();
+                    fields.insert(VALIDATION_FIELD_FIELD.to_string(), field);
+                    fields
+                });
+            }
+            AggregateError::DuplicateCommand(command_id) => {
+                body.variant = "DuplicateCommand".to_string();
+                let mut fields = HashMap::new();
+                fields.insert(DUPLICATE_COMMAND_ID_FIELD.to_string(), command_id);
+                body.fields = Some(fields);
+            }
+            AggregateError::ContextualError { inner, context } => {
+                body.variant = "ContextualError".to_string();
+                body.message = Some(context);
+                body.inner = Some(Box::new((*inner).into()));
+            }
+        }
+        body
+    }
+}
+
+impl TryFrom<AggregateErrorBody> for AggregateError {
+    type Error = String;
+
+    fn try_from(body: AggregateErrorBody) -> Result<Self, Self::Error> {
+        match body.variant.as_str() {
+            "UserError" => Ok(AggregateError::UserError(UserErrorPayload {
+                code: body.code,
+                message: body.message,
+                params: body.fields,
+            })),
+            "AggregateConflict" => Ok(AggregateError::AggregateConflict),
+            "TechnicalError" => Ok(AggregateError::TechnicalError(
+                body.message.unwrap_or_default(),
+            )),
+            "NotFound" => {
+                let aggregate_id = body
+                    .fields
+                    .and_then(|mut fields| fields.remove(NOT_FOUND_AGGREGATE_ID_FIELD))
+                    .unwrap_or_default();
+                Ok(AggregateError::NotFound(aggregate_id))
+            }
+            "ValidationError" => Ok(AggregateError::ValidationError(ValidationError {
+                field: body
+                    .fields
+                    .and_then(|mut fields| fields.remove(VALIDATION_FIELD_FIELD)),
+                message: body.message.unwrap_or_default(),
+            })),
+            "DuplicateCommand" => {
+                let command_id = body
+                    .fields
+                    .and_then(|mut fields| fields.remove(DUPLICATE_COMMAND_ID_FIELD))
+                    .unwrap_or_default();
+                Ok(AggregateError::DuplicateCommand(command_id))
+            }
+            "ContextualError" => match body.inner {
+                Some(inner) => Ok(AggregateError::ContextualError {
+                    inner: Box::new((*inner).try_into()?),
+                    context: body.message.unwrap_or_default(),
+                }),
+                None => Err("contextual error is missing the inner error".to_string()),
+            },
+            variant => Err(format!("unknown aggregate error variant: {}", variant)),
+        }
+    }
+}
+
 impl error::Error for AggregateError {}
 
 impl fmt::Display for AggregateError {
@@ -52,6 +371,16 @@ impl fmt::Display for AggregateError {
             AggregateError::TechnicalError(message) => write!(f, "{}", message),
             AggregateError::UserError(message) => write!(f, "{}", message),
             AggregateError::AggregateConflict => write!(f, "aggregate conflict"),
+            AggregateError::NotFound(aggregate_id) => {
+                write!(f, "aggregate not found: {}", aggregate_id)
+            }
+            AggregateError::ValidationError(err) => write!(f, "{}", err),
+            AggregateError::DuplicateCommand(command_id) => {
+                write!(f, "duplicate command: {}", command_id)
+            }
+            AggregateError::ContextualError { inner, context } => {
+                write!(f, "{}: {}", context, inner)
+            }
         }
     }
 }
@@ -95,6 +424,77 @@ impl AggregateError {
     }
 }
 
+impl AggregateError {
+    /// Adds context to this error, e.g., describing the operation that failed, while retaining
+    /// the original error. The context is prepended to the message of the original error.
+    ///
+    /// ```
+    /// # use cqrs_es::AggregateError;
+    /// let error = AggregateError::TechnicalError("connection refused".to_string())
+    ///     .context("loading events");
+    /// assert_eq!("loading events: connection refused", error.to_string());
+    ///
+    /// let error = AggregateError::new("user already exists").context("registering user");
+    /// assert!(error.is_user_error());
+    /// ```
+    #[must_use]
+    pub fn context(self, msg: impl Into<String>) -> Self {
+        AggregateError::ContextualError {
+            inner: Box::new(self),
+            context: msg.into(),
+        }
+    }
+    /// Returns `true` if this is a `UserError`, or a `ContextualError` wrapping a `UserError`.
+    ///
+    /// ```
+    /// # use cqrs_es::AggregateError;
+    /// assert!(AggregateError::new("user already exists").is_user_error());
+    /// ```
+    pub fn is_user_error(&self) -> bool {
+        match self {
+            AggregateError::UserError(_) => true,
+            AggregateError::ContextualError { inner, .. } => inner.is_user_error(),
+            _ => false,
+        }
+    }
+    /// Returns `true` if this is a `TechnicalError`, or a `ContextualError` wrapping a
+    /// `TechnicalError`.
+    ///
+    /// ```
+    /// # use cqrs_es::AggregateError;
+    /// let error = AggregateError::TechnicalError("connection refused".to_string())
+    ///     .context("loading events");
+    /// assert!(error.is_technical_error());
+    /// ```
+    pub fn is_technical_error(&self) -> bool {
+        match self {
+            AggregateError::TechnicalError(_) => true,
+            AggregateError::ContextualError { inner, .. } => inner.is_technical_error(),
+            _ => false,
+        }
+    }
+    /// The message meant to be returned to the user, if any. This is `Some` for a `UserError`
+    /// with a message or a `ValidationError`, or a `ContextualError` wrapping either, and `None`
+    /// for all other errors.
+    ///
+    /// ```
+    /// # use cqrs_es::AggregateError;
+    /// let error = AggregateError::new("user already exists");
+    /// assert_eq!(Some("user already exists"), error.user_message());
+    ///
+    /// let error = AggregateError::TechnicalError("connection refused".to_string());
+    /// assert_eq!(None, error.user_message());
+    /// ```
+    pub fn user_message(&self) -> Option<&str> {
+        match self {
+            AggregateError::UserError(payload) => payload.message.as_deref(),
+            AggregateError::ValidationError(err) => Some(err.message.as_str()),
+            AggregateError::ContextualError { inner, .. } => inner.user_message(),
+            _ => None,
+        }
+    }
+}
+
 impl From<&str> for AggregateError {
     fn from(message: &str) -> Self {
         AggregateError::UserError(UserErrorPayload {
@@ -105,13 +505,43 @@ impl From<&str> for AggregateError {
     }
 }
 
+/// A serialization failure, e.g., within an event store, is a `TechnicalError` holding the
+/// description of the failure. This allows `?` to be used on `serde_json` results in event store
+/// implementations.
 impl From<serde_json::error::Error> for AggregateError {
     fn from(err: serde_json::error::Error) -> Self {
-        match err.classify() {
-            serde_json::error::Category::Syntax => AggregateError::new("invalid json"),
-            serde_json::error::Category::Io
-            | serde_json::error::Category::Data
-            | serde_json::error::Category::Eof => AggregateError::new("fail"),
+        AggregateError::TechnicalError(err.to_string())
+    }
+}
+
+/// Allows an `AggregateError` to be returned directly from an `actix-web` handler, the response
+/// status is set according to the handling described for each variant. The body of a response
+/// with a server error status does not describe the failure, which is left to the logs. Available
+/// with the `actix-web` feature.
+#[cfg(feature = "actix-web")]
+impl actix_web::ResponseError for AggregateError {
+    fn status_code(&self) -> actix_web::http::StatusCode {
+        use actix_web::http::StatusCode;
+        match self {
+            AggregateError::UserError(_) => StatusCode::BAD_REQUEST,
+            AggregateError::ValidationError(_) => StatusCode::BAD_REQUEST,
+            AggregateError::AggregateConflict => StatusCode::CONFLICT,
+            AggregateError::NotFound(_) => StatusCode::NOT_FOUND,
+            AggregateError::DuplicateCommand(_) => StatusCode::CONFLICT,
+            AggregateError::ContextualError { inner, .. } => inner.status_code(),
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> actix_web::HttpResponse {
+        let mut response = actix_web::HttpResponse::build(self.status_code());
+        match self {
+            AggregateError::UserError(payload) => response.json(payload),
+            AggregateError::ValidationError(err) => response.json(err),
+            AggregateError::ContextualError { inner, .. } => inner.error_response(),
+            // the description of a technical error is not meant for clients
+            _ if self.status_code().is_server_error() => response.body("internal server error"),
+            _ => response.body(self.to_string()),
         }
     }
 }
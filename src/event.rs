@@ -1,10 +1,13 @@
 use std::collections::HashMap;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 
+use chrono::{DateTime, Utc};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use crate::aggregate::Aggregate;
+use crate::time_ordered::TIME_METADATA_KEY;
 
 /// A `DomainEvent` represents any business change in the state of an `Aggregate`. `DomainEvent`s
 /// are immutable and with [event sourcing](https://martinfowler.com/eaaDev/EventSourcing.html)
@@ -53,6 +56,34 @@ pub trait DomainEvent:
     fn event_type(&self) -> &'static str;
     /// A version of the `event_type`, use for event upcasting.
     fn event_version(&self) -> &'static str;
+    /// The names of the aggregate fields modified when this event is applied, for use by external
+    /// tooling such as static analysis and migration scripts. The framework does not use this
+    /// information, by default no fields are listed.
+    ///
+    /// ```ignore
+    /// fn aggregate_changes(&self) -> Vec<&'static str> {
+    ///     match self {
+    ///         CustomerEvent::NameChanged(_) => vec!["name"],
+    ///         CustomerEvent::EmailUpdated(_) => vec!["email", "email_verified"],
+    ///     }
+    /// }
+    /// ```
+    fn aggregate_changes(&self) -> Vec<&'static str> {
+        vec![]
+    }
+    /// Indicates whether this event is an implementation detail of the bounded context that
+    /// should not be published outside of it, by default `false`. Internal events are committed
+    /// and dispatched to queries as usual but are not written to an
+    /// [`OutboxEventStore`](struct.OutboxEventStore.html).
+    ///
+    /// ```ignore
+    /// fn is_internal(&self) -> bool {
+    ///     matches!(self, CustomerEvent::PasswordHashUpgraded { .. })
+    /// }
+    /// ```
+    fn is_internal(&self) -> bool {
+        false
+    }
 }
 
 /// `EventEnvelope` is a data structure that encapsulates an event with along with it's pertinent
@@ -60,7 +91,15 @@ pub trait DomainEvent:
 ///
 /// Within any system an event must be unique based on its' `aggregate_type`, `aggregate_id` and
 /// `sequence`.
-#[derive(Debug)]
+///
+/// Envelopes are equal only when all of their fields are equal, so a `HashSet` of envelopes
+/// retains the same event redelivered with differing metadata. Deduplicate by
+/// [`identity`](#method.identity) to retain a single copy of each event, see
+/// [`EventIdentity`](struct.EventIdentity.html).
+///
+/// The `Display` implementation never includes the payload, which may contain personally
+/// identifiable information, and is suitable for logging. With the `redacted-debug` feature
+/// enabled the payload is also omitted from the `Debug` output.
 pub struct EventEnvelope<A>
 where
     A: Aggregate,
@@ -95,6 +134,96 @@ impl<A: Aggregate> Clone for EventEnvelope<A> {
     }
 }
 
+impl<A: Aggregate> fmt::Display for EventEnvelope<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}:{} {} v{} metadata: {:?}",
+            self.aggregate_type,
+            self.aggregate_id,
+            self.sequence,
+            self.event_type,
+            self.event_version,
+            self.metadata
+        )
+    }
+}
+
+impl<A: Aggregate> fmt::Debug for EventEnvelope<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("EventEnvelope");
+        debug
+            .field("aggregate_id", &self.aggregate_id)
+            .field("sequence", &self.sequence)
+            .field("aggregate_type", &self.aggregate_type)
+            .field("event_type", &self.event_type)
+            .field("event_version", &self.event_version);
+        #[cfg(feature = "redacted-debug")]
+        debug.field("payload", &format_args!("<redacted>"));
+        #[cfg(not(feature = "redacted-debug"))]
+        debug.field("payload", &self.payload);
+        debug.field("metadata", &self.metadata).finish()
+    }
+}
+
+/// Envelopes are equal when all of their fields, including the payload and metadata, are equal.
+/// Use [`identity`](#method.identity) to compare or deduplicate envelopes by event identity
+/// alone.
+impl<A: Aggregate> PartialEq for EventEnvelope<A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.aggregate_id == other.aggregate_id
+            && self.sequence == other.sequence
+            && self.aggregate_type == other.aggregate_type
+            && self.event_type == other.event_type
+            && self.event_version == other.event_version
+            && self.payload == other.payload
+            && self.metadata == other.metadata
+    }
+}
+
+impl<A: Aggregate> Eq for EventEnvelope<A> {}
+
+/// Only the event identity is hashed, envelopes that are equal always share an identity. This
+/// does not make envelopes with the same identity equal, use `EventIdentity` for deduplication.
+impl<A: Aggregate> Hash for EventEnvelope<A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.identity().hash(state);
+    }
+}
+
+/// The stable identity of an event, composed of the `aggregate_id`, `sequence` and `event_type`
+/// of its envelope. This allows overlapping sets of events, e.g., from two sources publishing
+/// the same events with differing metadata, to be deduplicated within a `HashSet<EventIdentity>`
+/// where a `HashSet<EventEnvelope>` would retain both copies.
+///
+/// ```
+/// # use cqrs_es::doc::{Customer, CustomerEvent};
+/// use std::collections::HashSet;
+/// use cqrs_es::EventEnvelope;
+///
+/// let event = EventEnvelope::<Customer>::new(
+///     "test-aggregate-id-C450D1A".to_string(),
+///     1,
+///     "Customer".to_string(),
+///     CustomerEvent::NameAdded { changed_name: "John Doe".to_string() },
+/// );
+/// let republished = event.clone().with_metadata_entry("source", "replica");
+///
+/// assert_ne!(event, republished);
+/// let mut seen = HashSet::new();
+/// assert!(seen.insert(event.identity()));
+/// assert!(!seen.insert(republished.identity()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EventIdentity {
+    /// The id of the aggregate instance.
+    pub aggregate_id: String,
+    /// The sequence number for an aggregate instance.
+    pub sequence: usize,
+    /// The type of event.
+    pub event_type: String,
+}
+
 impl<A: Aggregate> EventEnvelope<A> {
     /// A convenience function for packaging an event in an `EventEnvelope`, used for
     /// testing `QueryProcessor`s.
@@ -133,4 +262,111 @@ impl<A: Aggregate> EventEnvelope<A> {
             metadata,
         }
     }
+    /// Adds a single metadata entry to the envelope, replacing any existing value for the key.
+    ///
+    /// ```
+    /// # use cqrs_es::doc::{Customer, CustomerEvent};
+    /// use cqrs_es::EventEnvelope;
+    ///
+    /// let envelope = EventEnvelope::<Customer>::new(
+    ///     "test-aggregate-id-C450D1A".to_string(),
+    ///     1,
+    ///     "Customer".to_string(),
+    ///     CustomerEvent::NameAdded { changed_name: "John Doe".to_string() },
+    /// )
+    /// .with_metadata_entry("user", "alice")
+    /// .with_metadata_entry("ip", "127.0.0.1");
+    /// assert_eq!("alice", envelope.metadata["user"]);
+    /// ```
+    #[must_use]
+    pub fn with_metadata_entry(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+    /// The metadata value held under the key, if present, borrowed from the metadata.
+    ///
+    /// ```
+    /// # use cqrs_es::doc::{Customer, CustomerEvent};
+    /// use cqrs_es::{EventEnvelope, TIME_METADATA_KEY};
+    ///
+    /// let envelope = EventEnvelope::<Customer>::new(
+    ///     "test-aggregate-id-C450D1A".to_string(),
+    ///     1,
+    ///     "Customer".to_string(),
+    ///     CustomerEvent::NameAdded { changed_name: "John Doe".to_string() },
+    /// )
+    /// .with_metadata_entry("user", "alice")
+    /// .with_metadata_entry(TIME_METADATA_KEY, "2021-03-18T12:32:45.930Z");
+    /// assert_eq!(Some("alice"), envelope.metadata_value("user"));
+    /// assert_eq!(None, envelope.metadata_value("ip"));
+    /// // prefer `occurred_at` for the parsed time of the event
+    /// assert_eq!(1616070765, envelope.occurred_at().unwrap().timestamp());
+    /// ```
+    #[inline]
+    pub fn metadata_value(&self, key: &str) -> Option<&str> {
+        self.metadata.get(key).map(String::as_str)
+    }
+    /// The identity of the event held in this envelope, see
+    /// [`EventIdentity`](struct.EventIdentity.html).
+    pub fn identity(&self) -> EventIdentity {
+        EventIdentity {
+            aggregate_id: self.aggregate_id.clone(),
+            sequence: self.sequence,
+            event_type: self.event_type.clone(),
+        }
+    }
+    /// The time at which the event occurred, parsed from the RFC 3339 timestamp held in the
+    /// metadata under the [`TIME_METADATA_KEY`](constant.TIME_METADATA_KEY.html). Returns `None`
+    /// if the timestamp is missing or malformed.
+    ///
+    /// ```
+    /// # use cqrs_es::doc::{Customer, CustomerEvent};
+    /// use std::collections::HashMap;
+    /// use cqrs_es::{EventEnvelope, TIME_METADATA_KEY};
+    ///
+    /// let mut metadata = HashMap::new();
+    /// metadata.insert(TIME_METADATA_KEY.to_string(), "2021-03-18T12:32:45.930Z".to_string());
+    /// let envelope = EventEnvelope::<Customer>::new_with_metadata(
+    ///     "test-aggregate-id-C450D1A".to_string(),
+    ///     1,
+    ///     "Customer".to_string(),
+    ///     CustomerEvent::NameAdded { changed_name: "John Doe".to_string() },
+    ///     metadata,
+    /// );
+    /// assert_eq!(1616070765, envelope.occurred_at().unwrap().timestamp());
+    /// ```
+    pub fn occurred_at(&self) -> Option<DateTime<Utc>> {
+        let time = self.metadata_value(TIME_METADATA_KEY)?;
+        DateTime::parse_from_rfc3339(time)
+            .ok()
+            .map(|time| time.with_timezone(&Utc))
+    }
+    /// Serializes the envelope, including the payload and metadata, to a JSON value for ad-hoc
+    /// inspection, e.g., in admin tooling or while debugging.
+    ///
+    /// ```
+    /// # use cqrs_es::doc::{Customer, CustomerEvent};
+    /// use cqrs_es::EventEnvelope;
+    ///
+    /// let envelope = EventEnvelope::<Customer>::new(
+    ///     "test-aggregate-id-C450D1A".to_string(),
+    ///     1,
+    ///     "Customer".to_string(),
+    ///     CustomerEvent::NameAdded { changed_name: "John Doe".to_string() },
+    /// );
+    /// let json = envelope.to_json().unwrap();
+    /// assert_eq!(1, json["sequence"]);
+    /// assert_eq!("John Doe", json["payload"]["NameAdded"]["changed_name"]);
+    /// ```
+    pub fn to_json(&self) -> Result<serde_json::Value, serde_json::Error> {
+        Ok(serde_json::json!({
+            "aggregate_id": self.aggregate_id,
+            "sequence": self.sequence,
+            "aggregate_type": self.aggregate_type,
+            "event_type": self.event_type,
+            "event_version": self.event_version,
+            "payload": serde_json::to_value(&self.payload)?,
+            "metadata": self.metadata,
+        }))
+    }
 }
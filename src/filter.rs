@@ -0,0 +1,96 @@
+use crate::event::EventEnvelope;
+use crate::query::EventPredicate;
+use crate::Aggregate;
+
+/// Composable criteria for selecting events, for use when rebuilding projections, paging through
+/// events or in debug tooling.
+///
+/// A new filter matches every event, each builder method further restricts the events that will
+/// match. Filters may be combined with `and` and `or`.
+///
+/// ```
+/// use cqrs_es::doc::Customer;
+/// use cqrs_es::EventFilter;
+///
+/// let filter = EventFilter::<Customer>::new()
+///     .event_type("NameAdded")
+///     .after_version(10)
+///     .or(EventFilter::new().with_metadata_key("reprocess", "true"));
+/// ```
+pub struct EventFilter<A: Aggregate> {
+    predicate: EventPredicate<A>,
+}
+
+impl<A: Aggregate + 'static> EventFilter<A> {
+    /// Creates a filter that matches all events.
+    pub fn new() -> Self {
+        EventFilter {
+            predicate: Box::new(|_| true),
+        }
+    }
+    /// Restricts the filter to events of the given event type.
+    #[must_use]
+    pub fn event_type(self, event_type: &str) -> Self {
+        let event_type = event_type.to_string();
+        self.matching(move |envelope| envelope.event_type == event_type)
+    }
+    /// Restricts the filter to events with a sequence number greater than `version`.
+    #[must_use]
+    pub fn after_version(self, version: usize) -> Self {
+        self.matching(move |envelope| envelope.sequence > version)
+    }
+    /// Restricts the filter to events with a sequence number less than `version`.
+    #[must_use]
+    pub fn before_version(self, version: usize) -> Self {
+        self.matching(move |envelope| envelope.sequence < version)
+    }
+    /// Restricts the filter to events with the given metadata entry.
+    #[must_use]
+    pub fn with_metadata_key(self, key: &str, value: &str) -> Self {
+        let key = key.to_string();
+        let value = value.to_string();
+        self.matching(move |envelope| envelope.metadata.get(&key) == Some(&value))
+    }
+    /// Restricts the filter to events for which the provided predicate returns `true`.
+    ///
+    /// ```
+    /// use cqrs_es::doc::{Customer, CustomerEvent};
+    /// use cqrs_es::EventFilter;
+    ///
+    /// let filter = EventFilter::<Customer>::new()
+    ///     .matching(|envelope| matches!(envelope.payload, CustomerEvent::EmailUpdated { .. }));
+    /// ```
+    #[must_use]
+    pub fn matching<F>(self, predicate: F) -> Self
+    where
+        F: Fn(&EventEnvelope<A>) -> bool + Send + Sync + 'static,
+    {
+        let current = self.predicate;
+        EventFilter {
+            predicate: Box::new(move |envelope| current(envelope) && predicate(envelope)),
+        }
+    }
+    /// Combines two filters, matching only events that match both.
+    #[must_use]
+    pub fn and(self, other: EventFilter<A>) -> Self {
+        self.matching(move |envelope| other.matches(envelope))
+    }
+    /// Combines two filters, matching events that match either.
+    #[must_use]
+    pub fn or(self, other: EventFilter<A>) -> Self {
+        let current = self.predicate;
+        EventFilter {
+            predicate: Box::new(move |envelope| current(envelope) || other.matches(envelope)),
+        }
+    }
+    /// Returns `true` if the event satisfies the filter.
+    pub fn matches(&self, envelope: &EventEnvelope<A>) -> bool {
+        (self.predicate)(envelope)
+    }
+}
+
+impl<A: Aggregate + 'static> Default for EventFilter<A> {
+    fn default() -> Self {
+        EventFilter::new()
+    }
+}
@@ -6,32 +6,110 @@
 #![doc = include_str!("../README.md")]
 //!
 pub use crate::aggregate::*;
+pub use crate::audit::*;
+pub use crate::checkpoint::*;
+pub use crate::command::*;
+pub use crate::compaction::*;
+pub use crate::compensation::*;
 pub use crate::cqrs::*;
+pub use crate::deduplicating::*;
+pub use crate::double_dispatch::*;
 pub use crate::error::*;
 pub use crate::event::*;
+pub use crate::filter::*;
+pub use crate::logging::*;
+pub use crate::migration::*;
+pub use crate::mirrored::*;
+pub use crate::outbox::*;
 pub use crate::query::*;
+pub use crate::rate_limit::*;
+pub use crate::read_model::*;
+pub use crate::serialization::*;
+pub use crate::shutdown::*;
+pub use crate::snapshot::*;
 pub use crate::store::*;
+pub use crate::tenant::*;
+pub use crate::time_ordered::*;
 
 // Aggregate module holds the central traits that define the fundamental component of CQRS.
 mod aggregate;
 
+// Audit provides a query recording the outcome of every command for use in tests.
+mod audit;
+
+// Command module provides the envelope used to submit a command along with its surrounding context.
+mod command;
+
 // Event module provides the abstract domain events and associated wrapper.
 mod event;
 
+// Filter provides composable criteria for selecting events.
+mod filter;
+
 // Store holds the abstact `EventStore` trait as well as an in-memory and Postgres implementation.
 mod store;
 
+// Logging provides an event store wrapper that logs store operations with `tracing`.
+mod logging;
+
+// Deduplicating provides an event store wrapper that commits each command only once.
+mod deduplicating;
+
+// Migration provides access to raw events for tooling that migrates between event schemas.
+mod migration;
+
+// Mirrored provides an event store that writes to two stores, for migrating between backends.
+mod mirrored;
+
+// Outbox provides an event store wrapper for reliably publishing committed events along with an
+// in-memory outbox for testing.
+mod outbox;
+
+// Tenant provides an event store wrapper isolating the events of each tenant.
+mod tenant;
+
+// Time ordered provides an event store wrapper enforcing strictly increasing commit timestamps.
+mod time_ordered;
+
+// Compaction provides an event store wrapper replacing old events with a state-carrying event.
+mod compaction;
+
+// Compensation provides the rollback of committed events when a saga step fails.
+mod compensation;
+
 // Cqrs provides the base framework and associated logic for processing loading aggregates via an
 // event store and subsequently processing commands.
 mod cqrs;
 
+// Rate limit provides a per-aggregate limit on the rate at which commands are applied.
+mod rate_limit;
+
+// Serialization provides the conversion of events to and from their persisted form.
+mod serialization;
+
+// Double dispatch provides a query for issuing commands to sibling aggregates in response to events.
+mod double_dispatch;
+
 // Aggregate error
 mod error;
 
+// Shutdown provides the handle used to drain in-flight commands before stopping.
+mod shutdown;
+
+// Snapshot provides the serialized form of aggregate state used for snapshots.
+mod snapshot;
+
 // Query provides the basic downstream query objects needed to render queries (or "views") that
 // describe the state of the system.
 mod query;
 
+// Checkpoint provides a query wrapper recording the progress of a projection so that a rebuild
+// may be resumed.
+mod checkpoint;
+
+// Read model provides persistence for the views maintained by projections.
+mod read_model;
+
 // Documentation items
 #[doc(hidden)]
 pub mod doc;
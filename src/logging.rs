@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use tracing::debug;
+
+use crate::event::EventEnvelope;
+use crate::{
+    Aggregate, AggregateContext, AggregateError, CountableEventStore, EventEnvelopeStream,
+    EventStore,
+};
+
+/// An event store wrapper that logs each `load`, `load_aggregate` and `commit` at the `debug`
+/// level using [`tracing`](https://docs.rs/tracing), including the aggregate ID, the number of
+/// events and the elapsed time.
+///
+/// When `debug` is disabled via `tracing`'s static level filtering (e.g., the
+/// `max_level_info` feature) the logging compiles away, only the timing remains.
+///
+/// ```
+/// # use cqrs_es::doc::MyAggregate;
+/// use cqrs_es::{CqrsFramework, LoggingEventStore};
+/// use cqrs_es::mem_store::MemStore;
+///
+/// let store = LoggingEventStore::new(MemStore::<MyAggregate>::default());
+/// let cqrs = CqrsFramework::new(store, vec![]);
+/// ```
+pub struct LoggingEventStore<ES> {
+    store: ES,
+}
+
+impl<ES> LoggingEventStore<ES> {
+    /// Wraps the provided event store, logging all operations.
+    pub fn new(store: ES) -> Self {
+        LoggingEventStore { store }
+    }
+}
+
+#[async_trait]
+impl<A, ES> EventStore<A> for LoggingEventStore<ES>
+where
+    A: Aggregate + 'static,
+    ES: EventStore<A> + 'static,
+{
+    type AC = ES::AC;
+
+    async fn load(&self, aggregate_id: &str) -> Vec<EventEnvelope<A>> {
+        let start = Instant::now();
+        let events = self.store.load(aggregate_id).await;
+        debug!(
+            aggregate_type = A::aggregate_type(),
+            aggregate_id,
+            event_count = events.len(),
+            elapsed = ?start.elapsed(),
+            "load"
+        );
+        events
+    }
+
+    fn load_stream<'a>(&'a self, aggregate_id: &'a str) -> EventEnvelopeStream<'a, A>
+    where
+        A: 'a,
+    {
+        self.store.load_stream(aggregate_id)
+    }
+
+    async fn load_aggregate(&self, aggregate_id: &str) -> Self::AC {
+        let start = Instant::now();
+        let context = self.store.load_aggregate(aggregate_id).await;
+        debug!(
+            aggregate_type = A::aggregate_type(),
+            aggregate_id,
+            elapsed = ?start.elapsed(),
+            "load_aggregate"
+        );
+        context
+    }
+
+    async fn last_event_version(&self, aggregate_id: &str) -> Option<usize> {
+        self.store.last_event_version(aggregate_id).await
+    }
+
+    async fn is_duplicate_command(&self, aggregate_id: &str, command_id: &str) -> bool {
+        self.store
+            .is_duplicate_command(aggregate_id, command_id)
+            .await
+    }
+
+    async fn commit(
+        &self,
+        events: Vec<A::Event>,
+        context: Self::AC,
+        metadata: HashMap<String, String>,
+    ) -> Result<Vec<EventEnvelope<A>>, AggregateError> {
+        let start = Instant::now();
+        let event_count = events.len();
+        let aggregate_id = context.aggregate_id().map(str::to_string);
+        let result = self.store.commit(events, context, metadata).await;
+        let aggregate_id = aggregate_id.as_deref();
+        debug!(
+            aggregate_type = A::aggregate_type(),
+            aggregate_id,
+            event_count,
+            success = result.is_ok(),
+            elapsed = ?start.elapsed(),
+            "commit"
+        );
+        result
+    }
+}
+
+#[async_trait]
+impl<A, ES> CountableEventStore<A> for LoggingEventStore<ES>
+where
+    A: Aggregate + 'static,
+    ES: CountableEventStore<A> + 'static,
+{
+    async fn count_events(&self) -> usize {
+        self.store.count_events().await
+    }
+}
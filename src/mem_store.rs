@@ -2,9 +2,17 @@ use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
 use async_trait::async_trait;
+use futures::stream;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
 
-use crate::event::EventEnvelope;
-use crate::{Aggregate, AggregateContext, AggregateError, EventStore};
+use crate::command::COMMAND_ID_METADATA_KEY;
+use crate::event::{DomainEvent, EventEnvelope};
+use crate::{
+    Aggregate, AggregateContext, AggregateError, BulkEventStore, CompactableEventStore,
+    CountableEventStore, EventEnvelopeStream, EventFilter, EventStore, EventStoreHealthCheck,
+    HealthCheckError, PaginatedEventStore, Query, Transactional,
+};
 
 ///  Simple memory store useful for application development and testing purposes.
 ///
@@ -17,20 +25,111 @@ use crate::{Aggregate, AggregateContext, AggregateError, EventStore};
 /// let store = MemStore::<MyAggregate>::default();
 /// let cqrs = CqrsFramework::new(store, vec![]);
 /// ```
+///
+/// Commits are idempotent when a command ID is provided in the metadata under the
+/// [`COMMAND_ID_METADATA_KEY`](../constant.COMMAND_ID_METADATA_KEY.html), a repeated commit with
+/// the same command ID for the same aggregate instance is rejected with an
+/// `AggregateError::DuplicateCommand` without committing the events again.
 pub struct MemStore<A: Aggregate + Send + Sync> {
     events: Arc<LockedEventEnvelopeMap<A>>,
+    commands: Arc<LockedCommandMap>,
+    commits: broadcast::Sender<Vec<EventEnvelope<A>>>,
 }
 
 impl<A: Aggregate> Default for MemStore<A> {
     fn default() -> Self {
         let events = Default::default();
-        MemStore { events }
+        let commands = Default::default();
+        let commits = broadcast::channel(COMMIT_SUBSCRIBER_CAPACITY).0;
+        MemStore {
+            events,
+            commands,
+            commits,
+        }
     }
 }
 
+// The number of committed batches retained for subscribers that have not yet received them.
+const COMMIT_SUBSCRIBER_CAPACITY: usize = 1024;
+
 type LockedEventEnvelopeMap<A> = RwLock<HashMap<String, Vec<EventEnvelope<A>>>>;
 
+// The sequence of the last event committed by each command, keyed by aggregate ID and command ID.
+type LockedCommandMap = RwLock<HashMap<(String, String), usize>>;
+
 impl<A: Aggregate> MemStore<A> {
+    /// Creates a store with space pre-allocated for at least `capacity` aggregate instances.
+    ///
+    /// This avoids repeatedly resizing the store in applications or benchmarks that are known to
+    /// create many thousands of aggregate instances.
+    /// ```
+    /// # use cqrs_es::doc::MyAggregate;
+    /// # use cqrs_es::mem_store::MemStore;
+    /// let store = MemStore::<MyAggregate>::with_capacity(10_000);
+    /// ```
+    pub fn with_capacity(capacity: usize) -> Self {
+        MemStore {
+            events: Arc::new(RwLock::new(HashMap::with_capacity(capacity))),
+            ..Self::default()
+        }
+    }
+
+    /// Creates a fully independent copy of the store, holding a copy of all events and
+    /// commands recorded so far. Commits to either store are not visible in the other, and
+    /// subscribers to the commits of this store do not receive the commits of the copy.
+    ///
+    /// This is useful for "forking" a store in tests where two execution paths are compared.
+    /// ```
+    /// # use cqrs_es::doc::MyAggregate;
+    /// # use cqrs_es::mem_store::MemStore;
+    /// let store = MemStore::<MyAggregate>::default();
+    /// //...
+    /// let fork = store.deep_clone();
+    /// ```
+    pub fn deep_clone(&self) -> Self {
+        // uninteresting unwrap: this will not be used in production, for tests only
+        let events = self.events.read().unwrap().clone();
+        // uninteresting unwrap: this will not be used in production, for tests only
+        let commands = self.commands.read().unwrap().clone();
+        MemStore {
+            events: Arc::new(RwLock::new(events)),
+            commands: Arc::new(RwLock::new(commands)),
+            ..Self::default()
+        }
+    }
+
+    /// Creates a store pre-populated with events for each of the provided aggregate IDs.
+    /// Events are stored in the order provided, sequenced from 1 and with no metadata.
+    ///
+    /// This simplifies the fixture setup for integration tests.
+    /// ```
+    /// # use cqrs_es::doc::{Customer, CustomerEvent};
+    /// # use cqrs_es::mem_store::MemStore;
+    /// use std::collections::HashMap;
+    ///
+    /// let mut seed_events = HashMap::new();
+    /// seed_events.insert(
+    ///     "test-aggregate-id-C450D1A".to_string(),
+    ///     vec![CustomerEvent::NameAdded {
+    ///         changed_name: "John Doe".to_string(),
+    ///     }],
+    /// );
+    /// let store = MemStore::<Customer>::with_seed_events(seed_events);
+    /// ```
+    pub fn with_seed_events(events: HashMap<String, Vec<A::Event>>) -> Self {
+        let store = Self::default();
+        {
+            // uninteresting unwrap: this will not be used in production, for tests only
+            let mut event_map = store.events.write().unwrap();
+            for (aggregate_id, events) in events {
+                let wrapped_events =
+                    store.wrap_events(&aggregate_id, 0, events, HashMap::default());
+                event_map.insert(aggregate_id, wrapped_events);
+            }
+        }
+        store
+    }
+
     /// Get a shared copy of the events stored within the event store.
     ///
     /// This can be used to verify the state of events that have been committed.
@@ -56,6 +155,245 @@ impl<A: Aggregate> MemStore<A> {
         Arc::clone(&self.events)
     }
 
+    /// Provides the IDs of all aggregate instances that have events within the store.
+    ///
+    /// Only the keys of the store are copied, the events themselves are not cloned.
+    /// ```
+    /// # use cqrs_es::doc::MyAggregate;
+    /// # use cqrs_es::mem_store::MemStore;
+    /// let store = MemStore::<MyAggregate>::default();
+    /// //...
+    /// for aggregate_id in store.aggregate_ids() {
+    ///     println!("{}", aggregate_id);
+    /// }
+    /// ```
+    pub fn aggregate_ids(&self) -> Vec<String> {
+        // uninteresting unwrap: this will not be used in production, for tests only
+        let event_map = self.events.read().unwrap();
+        event_map.keys().cloned().collect()
+    }
+
+    /// Dispatches all stored events to the query, one aggregate instance at a time in order of
+    /// aggregate ID, with the events of each instance in sequence order. As with a
+    /// `CqrsFramework`, only aggregate instances that the query
+    /// [subscribes to](../trait.Query.html#method.subscribes_to) are dispatched.
+    ///
+    /// This allows a projection to be rebuilt from the store in a single call.
+    /// ```ignore
+    /// let view = CustomerView::default();
+    /// store.replay_to(&view).await;
+    /// ```
+    pub async fn replay_to(&self, query: &dyn Query<A>) {
+        let mut events: Vec<(String, Vec<EventEnvelope<A>>)> = {
+            // uninteresting unwrap: this will not be used in production, for tests only
+            let event_map = self.events.read().unwrap();
+            event_map
+                .iter()
+                .filter(|(aggregate_id, _)| query.subscribes_to(aggregate_id))
+                .map(|(aggregate_id, events)| (aggregate_id.clone(), events.clone()))
+                .collect()
+        };
+        events.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (aggregate_id, events) in events {
+            query.dispatch(&aggregate_id, &events).await;
+        }
+    }
+
+    /// Removes all events for a single aggregate instance, leaving the events of all other
+    /// aggregate instances in place. Returns `true` if any events were removed. Any record of the
+    /// commands that produced the removed events is also dropped so that those commands may be
+    /// executed again.
+    ///
+    /// This is useful when a store is shared across several tests.
+    /// ```
+    /// # use cqrs_es::doc::MyAggregate;
+    /// # use cqrs_es::mem_store::MemStore;
+    /// let store = MemStore::<MyAggregate>::default();
+    /// //...
+    /// assert!(!store.clear_aggregate("test-aggregate-id-C450D1A"));
+    /// ```
+    pub fn clear_aggregate(&self, aggregate_id: &str) -> bool {
+        // uninteresting unwrap: this will not be used in production, for tests only
+        let mut event_map = self.events.write().unwrap();
+        let removed = event_map.remove(aggregate_id).is_some();
+        self.forget_removed_commands(&event_map);
+        removed
+    }
+
+    /// Removes the last `n_events` events of an aggregate instance, simulating the rollback of a
+    /// commit when testing compensating transactions. Any record of the commands that produced
+    /// the removed events is also dropped so that those commands may be retried.
+    ///
+    /// An `AggregateError::TechnicalError` is returned, and no events are removed, if the aggregate
+    /// instance has fewer than `n_events` events.
+    /// ```
+    /// # use cqrs_es::doc::MyAggregate;
+    /// # use cqrs_es::mem_store::MemStore;
+    /// let store = MemStore::<MyAggregate>::default();
+    /// //...
+    /// assert!(store.rollback_last_commit("test-aggregate-id-C450D1A", 1).is_err());
+    /// ```
+    pub fn rollback_last_commit(
+        &self,
+        aggregate_id: &str,
+        n_events: usize,
+    ) -> Result<(), AggregateError> {
+        // uninteresting unwrap: this will not be used in production, for tests only
+        let mut event_map = self.events.write().unwrap();
+        let current_events = event_map.get(aggregate_id).map_or(0, Vec::len);
+        if current_events < n_events {
+            return Err(AggregateError::TechnicalError(format!(
+                "cannot roll back {} events, aggregate '{}' has only {} events",
+                n_events, aggregate_id, current_events
+            )));
+        }
+        let remaining = current_events - n_events;
+        if remaining == 0 {
+            event_map.remove(aggregate_id);
+        } else if let Some(events) = event_map.get_mut(aggregate_id) {
+            events.truncate(remaining);
+        }
+        self.forget_removed_commands(&event_map);
+        Ok(())
+    }
+
+    /// Subscribes to each batch of events committed to the store after the call to
+    /// `subscribe_to_commits`, batches are sent immediately after they are committed.
+    ///
+    /// This allows tests to wait for a commit rather than sleeping for an arbitrary time. A
+    /// subscriber that falls more than 1024 batches behind will miss the oldest batches.
+    /// ```ignore
+    /// let mut commits = store.subscribe_to_commits();
+    /// //...
+    /// let committed_events = commits.recv().await.unwrap();
+    /// ```
+    pub fn subscribe_to_commits(&self) -> broadcast::Receiver<Vec<EventEnvelope<A>>> {
+        self.commits.subscribe()
+    }
+
+    /// The most recently committed event for an aggregate instance, if any.
+    ///
+    /// Only the last event is cloned, this avoids loading the full event stream when only the
+    /// latest event is of interest.
+    /// ```
+    /// # use cqrs_es::doc::MyAggregate;
+    /// # use cqrs_es::mem_store::MemStore;
+    /// let store = MemStore::<MyAggregate>::default();
+    /// //...
+    /// assert!(store.get_latest_event("test-aggregate-id-C450D1A").is_none());
+    /// ```
+    pub fn get_latest_event(&self, aggregate_id: &str) -> Option<EventEnvelope<A>> {
+        // uninteresting unwrap: this will not be used in production, for tests only
+        let event_map = self.events.read().unwrap();
+        event_map.get(aggregate_id)?.last().cloned()
+    }
+
+    /// The number of events of the given type that have been committed for an aggregate instance.
+    ///
+    /// This is useful for verifying that an event was produced an exact number of times.
+    /// ```
+    /// # use cqrs_es::doc::MyAggregate;
+    /// # use cqrs_es::mem_store::MemStore;
+    /// let store = MemStore::<MyAggregate>::default();
+    /// //...
+    /// let count = store.event_count_for_type("test-aggregate-id-C450D1A", "SomethingWasDone");
+    /// ```
+    pub fn event_count_for_type(&self, aggregate_id: &str, event_type: &str) -> usize {
+        // uninteresting unwrap: this will not be used in production, for tests only
+        let event_map = self.events.read().unwrap();
+        match event_map.get(aggregate_id) {
+            Some(events) => events
+                .iter()
+                .filter(|envelope| envelope.payload.event_type() == event_type)
+                .count(),
+            None => 0,
+        }
+    }
+
+    /// The IDs of all aggregate instances with at least one event of the given type, each ID is
+    /// returned only once regardless of how many matching events it has.
+    ///
+    /// This is useful for finding all aggregates of a certain kind, e.g., all cancelled orders.
+    /// ```
+    /// # use cqrs_es::doc::MyAggregate;
+    /// # use cqrs_es::mem_store::MemStore;
+    /// let store = MemStore::<MyAggregate>::default();
+    /// //...
+    /// let aggregate_ids = store.find_aggregates_with_event_type("SomethingWasDone");
+    /// ```
+    pub fn find_aggregates_with_event_type(&self, event_type: &str) -> Vec<String> {
+        // uninteresting unwrap: this will not be used in production, for tests only
+        let event_map = self.events.read().unwrap();
+        event_map
+            .iter()
+            .filter(|(_, events)| {
+                events
+                    .iter()
+                    .any(|envelope| envelope.payload.event_type() == event_type)
+            })
+            .map(|(aggregate_id, _)| aggregate_id.clone())
+            .collect()
+    }
+
+    /// Loads the events for an aggregate instance that match the provided filter.
+    ///
+    /// ```
+    /// # use cqrs_es::doc::MyAggregate;
+    /// # use cqrs_es::mem_store::MemStore;
+    /// use cqrs_es::EventFilter;
+    ///
+    /// let store = MemStore::<MyAggregate>::default();
+    /// //...
+    /// let filter = EventFilter::new().event_type("SomethingWasDone").after_version(2);
+    /// let events = store.load_filtered_structured("test-aggregate-id-C450D1A", &filter);
+    /// ```
+    pub fn load_filtered_structured(
+        &self,
+        aggregate_id: &str,
+        filter: &EventFilter<A>,
+    ) -> Vec<EventEnvelope<A>>
+    where
+        A: 'static,
+    {
+        // uninteresting unwrap: this will not be used in production, for tests only
+        let event_map = self.events.read().unwrap();
+        match event_map.get(aggregate_id) {
+            Some(events) => events
+                .iter()
+                .filter(|envelope| filter.matches(envelope))
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Performs multiple operations on the stored events atomically, the write lock is held for
+    /// the duration of `f` so no other reads or commits may interleave. Any record of the commands
+    /// that produced events removed by `f` is dropped afterwards.
+    ///
+    /// This is useful for multi-threaded integration tests that must set up the events of several
+    /// aggregates consistently.
+    /// ```
+    /// # use cqrs_es::doc::MyAggregate;
+    /// # use cqrs_es::mem_store::MemStore;
+    /// let store = MemStore::<MyAggregate>::default();
+    /// //...
+    /// let total_events = store.transaction(|events| {
+    ///     events.remove("test-aggregate-id-C450D1A");
+    ///     events.values().map(Vec::len).sum::<usize>()
+    /// });
+    /// ```
+    pub fn transaction<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut HashMap<String, Vec<EventEnvelope<A>>>) -> R,
+    {
+        // uninteresting unwrap: this will not be used in production, for tests only
+        let mut event_map = self.events.write().unwrap();
+        let result = f(&mut event_map);
+        self.forget_removed_commands(&event_map);
+        result
+    }
+
     fn load_commited_events(&self, aggregate_id: String) -> Vec<EventEnvelope<A>> {
         // uninteresting unwrap: this will not be used in production, for tests only
         let event_map = self.events.read().unwrap();
@@ -70,6 +408,18 @@ impl<A: Aggregate> MemStore<A> {
         };
         committed_events
     }
+    // Drops the record of any command whose events are no longer stored, allowing the command to
+    // be executed again. The write lock on the events must be held by the caller.
+    fn forget_removed_commands(&self, event_map: &HashMap<String, Vec<EventEnvelope<A>>>) {
+        // uninteresting unwrap: this will not be used in production, for tests only
+        let mut command_map = self.commands.write().unwrap();
+        command_map.retain(|(aggregate_id, _), sequence| {
+            event_map
+                .get(aggregate_id)
+                .and_then(|events| events.last())
+                .is_some_and(|envelope| envelope.sequence >= *sequence)
+        });
+    }
     fn aggregate_id(&self, events: &[EventEnvelope<A>]) -> String {
         // uninteresting unwrap: this is not a struct for production use
         let &first_event = events.iter().peekable().peek().unwrap();
@@ -91,20 +441,45 @@ impl<A: Aggregate> EventStore<A> for MemStore<A> {
         events
     }
 
+    fn load_stream<'a>(&'a self, aggregate_id: &'a str) -> EventEnvelopeStream<'a, A>
+    where
+        A: 'a,
+    {
+        let events = self.load_commited_events(aggregate_id.to_string());
+        Box::pin(stream::iter(events))
+    }
+
     async fn load_aggregate(&self, aggregate_id: &str) -> MemStoreAggregateContext<A> {
         let committed_events = self.load(aggregate_id).await;
+        let mut context = MemStoreAggregateContext {
+            aggregate_id: aggregate_id.to_string(),
+            aggregate: A::default(),
+            current_sequence: 0,
+        };
         let mut aggregate = A::default();
-        let mut current_sequence = 0;
+        context.set_id_on_aggregate(&mut aggregate);
         for envelope in committed_events {
-            current_sequence = envelope.sequence;
+            context.current_sequence = envelope.sequence;
             let event = envelope.payload;
             aggregate.apply(event);
         }
-        MemStoreAggregateContext {
-            aggregate_id: aggregate_id.to_string(),
-            aggregate,
-            current_sequence,
-        }
+        context.aggregate = aggregate;
+        context
+    }
+
+    async fn last_event_version(&self, aggregate_id: &str) -> Option<usize> {
+        // uninteresting unwrap: this will not be used in production, for tests only
+        let event_map = self.events.read().unwrap();
+        event_map
+            .get(aggregate_id)
+            .and_then(|events| events.last())
+            .map(|envelope| envelope.sequence)
+    }
+
+    async fn is_duplicate_command(&self, aggregate_id: &str, command_id: &str) -> bool {
+        // uninteresting unwrap: this will not be used in production, for tests only
+        let command_map = self.commands.read().unwrap();
+        command_map.contains_key(&(aggregate_id.to_string(), command_id.to_string()))
     }
 
     async fn commit(
@@ -113,32 +488,262 @@ impl<A: Aggregate> EventStore<A> for MemStore<A> {
         context: MemStoreAggregateContext<A>,
         metadata: HashMap<String, String>,
     ) -> Result<Vec<EventEnvelope<A>>, AggregateError> {
+        let command_id = metadata.get(COMMAND_ID_METADATA_KEY).cloned();
         let aggregate_id = context.aggregate_id.as_str();
         let current_sequence = context.current_sequence;
         let wrapped_events = self.wrap_events(aggregate_id, current_sequence, events, metadata);
+        // the check for a duplicate command and the commit are made under the same locks
+        // uninteresting unwrap: this is not a struct for production use
+        let mut event_map = self.events.write().unwrap();
+        // uninteresting unwrap: this is not a struct for production use
+        let mut command_map = self.commands.write().unwrap();
+        let command_key = command_id.map(|command_id| (aggregate_id.to_string(), command_id));
+        if let Some(command_key) = &command_key {
+            if command_map.contains_key(command_key) {
+                println!(
+                    "ignoring: duplicate commit for command ID '{}' on aggregate ID '{}'",
+                    command_key.1, aggregate_id
+                );
+                return Err(AggregateError::DuplicateCommand(command_key.1.clone()));
+            }
+        }
         let new_events_qty = wrapped_events.len();
         if new_events_qty == 0 {
             return Ok(Vec::default());
         }
         let aggregate_id = self.aggregate_id(&wrapped_events);
-        let mut new_events = self.load_commited_events(aggregate_id.to_string());
-        for event in &wrapped_events {
-            new_events.push(event.clone());
-        }
         println!(
             "storing: {} new events for aggregate ID '{}'",
             new_events_qty, &aggregate_id
         );
-        // uninteresting unwrap: this is not a struct for production use
-        let mut event_map = self.events.write().unwrap();
-        event_map.insert(aggregate_id, new_events);
+        event_map
+            .entry(aggregate_id)
+            .or_default()
+            .extend(wrapped_events.iter().cloned());
+        if let Some(command_key) = command_key {
+            // uninteresting unwrap: at least one event has been committed
+            let last_sequence = wrapped_events.last().unwrap().sequence;
+            command_map.insert(command_key, last_sequence);
+        }
+        if self.commits.receiver_count() > 0 {
+            // an error here only indicates that all subscribers have since been dropped
+            let _ = self.commits.send(wrapped_events.clone());
+        }
+        Ok(wrapped_events)
+    }
+}
+
+#[async_trait]
+impl<A: Aggregate> CountableEventStore<A> for MemStore<A> {
+    async fn count_events(&self) -> usize {
+        // uninteresting unwrap: this will not be used in production, for tests only
+        let event_map = self.events.read().unwrap();
+        event_map.values().map(Vec::len).sum()
+    }
+}
+
+#[async_trait]
+impl<A: Aggregate> PaginatedEventStore<A> for MemStore<A> {
+    async fn load_page(
+        &self,
+        aggregate_id: &str,
+        page: usize,
+        page_size: usize,
+    ) -> (Vec<EventEnvelope<A>>, usize) {
+        // uninteresting unwrap: this will not be used in production, for tests only
+        let event_map = self.events.read().unwrap();
+        let events = match event_map.get(aggregate_id) {
+            Some(events) => events.as_slice(),
+            None => &[],
+        };
+        let start = page.saturating_mul(page_size).min(events.len());
+        let end = start.saturating_add(page_size).min(events.len());
+        (events[start..end].to_vec(), events.len())
+    }
+}
+
+#[async_trait]
+impl<A: Aggregate> BulkEventStore<A> for MemStore<A> {
+    async fn load_many(&self, aggregate_ids: &[&str]) -> HashMap<String, Vec<EventEnvelope<A>>> {
+        // uninteresting unwrap: this will not be used in production, for tests only
+        let event_map = self.events.read().unwrap();
+        aggregate_ids
+            .iter()
+            .map(|aggregate_id| {
+                let events = event_map.get(*aggregate_id).cloned().unwrap_or_default();
+                (aggregate_id.to_string(), events)
+            })
+            .collect()
+    }
+}
+
+// The sequence of the last pending event of each command within a transaction, keyed by aggregate
+// ID and command ID.
+fn transaction_commands<A: Aggregate>(
+    pending: &[(usize, Vec<EventEnvelope<A>>)],
+) -> HashMap<(String, String), usize> {
+    let mut commands = HashMap::new();
+    for envelope in pending
+        .iter()
+        .flat_map(|(_, pending_events)| pending_events)
+    {
+        if let Some(command_id) = envelope.metadata.get(COMMAND_ID_METADATA_KEY) {
+            let command_key = (envelope.aggregate_id.clone(), command_id.clone());
+            commands.insert(command_key, envelope.sequence);
+        }
+    }
+    commands
+}
+
+/// The pending changes of a `MemStore` transaction, see
+/// [`Transactional`](../trait.Transactional.html).
+pub struct MemStoreTransaction<A: Aggregate> {
+    // the expected sequence of each aggregate in the store, along with its pending events
+    pending: Vec<(usize, Vec<EventEnvelope<A>>)>,
+}
+
+#[async_trait]
+impl<A: Aggregate> Transactional<A> for MemStore<A> {
+    type Transaction = MemStoreTransaction<A>;
+
+    async fn begin(&self) -> MemStoreTransaction<A> {
+        MemStoreTransaction {
+            pending: Vec::new(),
+        }
+    }
+
+    async fn commit_in_transaction(
+        &self,
+        tx: &mut MemStoreTransaction<A>,
+        events: Vec<A::Event>,
+        context: MemStoreAggregateContext<A>,
+        metadata: HashMap<String, String>,
+    ) -> Result<Vec<EventEnvelope<A>>, AggregateError> {
+        let aggregate_id = context.aggregate_id.as_str();
+        if let Some(command_id) = metadata.get(COMMAND_ID_METADATA_KEY) {
+            let command_key = (aggregate_id.to_string(), command_id.clone());
+            // uninteresting unwrap: this will not be used in production, for tests only
+            let committed = self.commands.read().unwrap().contains_key(&command_key);
+            if committed || transaction_commands(&tx.pending).contains_key(&command_key) {
+                return Err(AggregateError::DuplicateCommand(command_id.clone()));
+            }
+        }
+        let pending = tx.pending.iter_mut().find(|(_, pending_events)| {
+            pending_events
+                .first()
+                .is_some_and(|event| event.aggregate_id == aggregate_id)
+        });
+        let wrapped_events = match pending {
+            // events committed within the transaction follow any already pending
+            Some((_, pending_events)) => {
+                let current_sequence = pending_events.last().map_or(0, |event| event.sequence);
+                let wrapped_events =
+                    self.wrap_events(aggregate_id, current_sequence, events, metadata);
+                pending_events.extend(wrapped_events.iter().cloned());
+                wrapped_events
+            }
+            None => {
+                let wrapped_events =
+                    self.wrap_events(aggregate_id, context.current_sequence, events, metadata);
+                if !wrapped_events.is_empty() {
+                    tx.pending
+                        .push((context.current_sequence, wrapped_events.clone()));
+                }
+                wrapped_events
+            }
+        };
         Ok(wrapped_events)
     }
+
+    async fn commit_transaction(&self, tx: MemStoreTransaction<A>) -> Result<(), AggregateError> {
+        // uninteresting unwrap: this will not be used in production, for tests only
+        let mut event_map = self.events.write().unwrap();
+        for (expected_sequence, pending_events) in &tx.pending {
+            let aggregate_id = self.aggregate_id(pending_events);
+            let current_sequence = event_map
+                .get(&aggregate_id)
+                .and_then(|events| events.last())
+                .map_or(0, |event| event.sequence);
+            if current_sequence != *expected_sequence {
+                return Err(AggregateError::AggregateConflict);
+            }
+        }
+        // uninteresting unwrap: this will not be used in production, for tests only
+        let mut command_map = self.commands.write().unwrap();
+        let tx_commands = transaction_commands(&tx.pending);
+        if let Some((_, command_id)) = tx_commands.keys().find(|key| command_map.contains_key(key))
+        {
+            return Err(AggregateError::DuplicateCommand(command_id.clone()));
+        }
+        command_map.extend(tx_commands);
+        for (_, pending_events) in tx.pending {
+            let aggregate_id = self.aggregate_id(&pending_events);
+            println!(
+                "storing: {} new events for aggregate ID '{}' in transaction",
+                pending_events.len(),
+                &aggregate_id
+            );
+            event_map
+                .entry(aggregate_id)
+                .or_default()
+                .extend(pending_events.iter().cloned());
+            if self.commits.receiver_count() > 0 {
+                // an error here only indicates that all subscribers have since been dropped
+                let _ = self.commits.send(pending_events);
+            }
+        }
+        Ok(())
+    }
+
+    async fn rollback_transaction(&self, tx: MemStoreTransaction<A>) {
+        println!(
+            "discarding: transaction with events for {} aggregates",
+            tx.pending.len()
+        );
+    }
+}
+
+#[async_trait]
+impl<A: Aggregate> CompactableEventStore<A> for MemStore<A> {
+    async fn replace_events(
+        &self,
+        aggregate_id: &str,
+        through_sequence: usize,
+        events: Vec<EventEnvelope<A>>,
+    ) -> Result<(), AggregateError> {
+        // uninteresting unwrap: this will not be used in production, for tests only
+        let mut event_map = self.events.write().unwrap();
+        let stored_events = event_map
+            .get_mut(aggregate_id)
+            .ok_or(AggregateError::AggregateConflict)?;
+        let replaced = stored_events
+            .iter()
+            .position(|envelope| envelope.sequence == through_sequence)
+            .ok_or(AggregateError::AggregateConflict)?;
+        stored_events.splice(..=replaced, events);
+        if stored_events.is_empty() {
+            event_map.remove(aggregate_id);
+        }
+        self.forget_removed_commands(&event_map);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<A: Aggregate> EventStoreHealthCheck for MemStore<A> {
+    async fn ping(&self) -> Result<(), HealthCheckError> {
+        Ok(())
+    }
 }
 
 /// Holds context for a pure event store implementation for MemStore.
 ///
 /// This is used internally by the `CqrsFramework`.
+///
+/// The context may be serialized, allowing a stateless service to cache it externally between
+/// requests rather than reloading the aggregate.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct MemStoreAggregateContext<A>
 where
     A: Aggregate,
@@ -158,4 +763,16 @@ where
     fn aggregate(&self) -> &A {
         &self.aggregate
     }
+
+    fn aggregate_id(&self) -> Option<&str> {
+        Some(&self.aggregate_id)
+    }
+
+    fn current_sequence(&self) -> Option<usize> {
+        Some(self.current_sequence)
+    }
+
+    fn set_id_on_aggregate(&self, aggregate: &mut A) {
+        aggregate.set_aggregate_id(&self.aggregate_id);
+    }
 }
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::event::EventEnvelope;
+use crate::{Aggregate, AggregateError};
+
+/// An event envelope holding the payload in its raw JSON form, for migration tooling that must
+/// read events persisted with a schema version that no longer matches the current event type.
+///
+/// ```
+/// # use cqrs_es::doc::{Customer, CustomerEvent};
+/// use cqrs_es::{EventEnvelope, RawEventEnvelope};
+///
+/// let envelope = EventEnvelope::<Customer>::new(
+///     "test-aggregate-id-C450D1A".to_string(),
+///     1,
+///     "Customer".to_string(),
+///     CustomerEvent::NameAdded { changed_name: "John Doe".to_string() },
+/// );
+/// let raw = RawEventEnvelope::from_envelope(&envelope).unwrap();
+/// assert_eq!("John Doe", raw.payload["NameAdded"]["changed_name"]);
+/// assert_eq!(envelope, raw.into_envelope::<Customer>().unwrap());
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RawEventEnvelope {
+    /// The id of the aggregate instance.
+    pub aggregate_id: String,
+    /// The sequence number for an aggregate instance.
+    pub sequence: usize,
+    /// The type of aggregate the event applies to.
+    pub aggregate_type: String,
+    /// The type of event.
+    pub event_type: String,
+    /// The event version.
+    pub event_version: String,
+    /// The serialized event payload.
+    pub payload: Value,
+    /// Additional metadata for use in auditing, logging or debugging purposes.
+    pub metadata: HashMap<String, String>,
+}
+
+impl RawEventEnvelope {
+    /// Creates a raw envelope from an event envelope, serializing the payload.
+    pub fn from_envelope<A: Aggregate>(
+        envelope: &EventEnvelope<A>,
+    ) -> Result<Self, serde_json::Error> {
+        Ok(RawEventEnvelope {
+            aggregate_id: envelope.aggregate_id.clone(),
+            sequence: envelope.sequence,
+            aggregate_type: envelope.aggregate_type.clone(),
+            event_type: envelope.event_type.clone(),
+            event_version: envelope.event_version.clone(),
+            payload: serde_json::to_value(&envelope.payload)?,
+            metadata: envelope.metadata.clone(),
+        })
+    }
+    /// Deserializes the payload into the current event type of the aggregate, this fails if the
+    /// payload does not match the current schema of the event.
+    pub fn into_envelope<A: Aggregate>(self) -> Result<EventEnvelope<A>, serde_json::Error> {
+        Ok(EventEnvelope {
+            aggregate_id: self.aggregate_id,
+            sequence: self.sequence,
+            aggregate_type: self.aggregate_type,
+            event_type: self.event_type,
+            event_version: self.event_version,
+            payload: serde_json::from_value(self.payload)?,
+            metadata: self.metadata,
+        })
+    }
+}
+
+/// Converts events from one schema version to another within migration tooling, e.g., from a
+/// `RawEventEnvelope` persisted with an old schema to an `EventEnvelope` of the current schema.
+///
+/// ```
+/// # use cqrs_es::doc::Customer;
+/// use cqrs_es::{AggregateError, EventEnvelope, EventMigrator, RawEventEnvelope};
+///
+/// struct NameAddedV1Migrator;
+///
+/// impl EventMigrator<RawEventEnvelope, EventEnvelope<Customer>> for NameAddedV1Migrator {
+///     fn migrate(&self, mut event: RawEventEnvelope) -> Result<EventEnvelope<Customer>, AggregateError> {
+///         if event.event_type == "NameAdded" && event.event_version == "1.0" {
+///             let name = event.payload["NameAdded"]["name"].take();
+///             event.payload = serde_json::json!({ "NameAdded": { "changed_name": name } });
+///             event.event_version = "2.0".to_string();
+///         }
+///         Ok(event.into_envelope()?)
+///     }
+/// }
+/// ```
+pub trait EventMigrator<Old, New> {
+    /// Converts a single event to the new schema.
+    fn migrate(&self, event: Old) -> Result<New, AggregateError>;
+    /// Converts each of the events in order, stopping at the first failure.
+    fn migrate_all(&self, events: Vec<Old>) -> Result<Vec<New>, AggregateError> {
+        events
+            .into_iter()
+            .map(|event| self.migrate(event))
+            .collect()
+    }
+}
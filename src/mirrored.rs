@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use tracing::warn;
+
+use crate::event::EventEnvelope;
+use crate::{
+    Aggregate, AggregateContext, AggregateError, CountableEventStore, EventEnvelopeStream,
+    EventStore,
+};
+
+/// An event store that writes to two stores while reading exclusively from the primary, for use
+/// when migrating between event store backends without downtime.
+///
+/// Events are committed to the primary store first, a failure there fails the commit and nothing
+/// is written to the secondary store. The same events and metadata are then committed to the
+/// secondary store, provided that its events for the aggregate instance end immediately before
+/// the sequence of the events committed to the primary. A secondary store that is out of step
+/// with the primary, e.g., because an earlier commit to it failed, is not written to and this is
+/// treated as a failure of the secondary store. By default a failure of the secondary store is
+/// logged and ignored, use `propagate_secondary_failures` to return these failures instead.
+///
+/// ```
+/// # use cqrs_es::doc::MyAggregate;
+/// use cqrs_es::{CqrsFramework, MirroredEventStore};
+/// use cqrs_es::mem_store::MemStore;
+///
+/// let store = MirroredEventStore::new(
+///     MemStore::<MyAggregate>::default(),
+///     MemStore::<MyAggregate>::default(),
+/// )
+/// .propagate_secondary_failures();
+/// let cqrs = CqrsFramework::new(store, vec![]);
+/// ```
+pub struct MirroredEventStore<P, S> {
+    primary: P,
+    secondary: S,
+    propagate_secondary_failures: bool,
+}
+
+impl<P, S> MirroredEventStore<P, S> {
+    /// Creates a store mirroring all commits from the primary store to the secondary store.
+    pub fn new(primary: P, secondary: S) -> Self {
+        MirroredEventStore {
+            primary,
+            secondary,
+            propagate_secondary_failures: false,
+        }
+    }
+    /// Returns any failure to commit to the secondary store rather than ignoring it. Note that the
+    /// events will have already been committed to the primary store.
+    #[must_use]
+    pub fn propagate_secondary_failures(mut self) -> Self {
+        self.propagate_secondary_failures = true;
+        self
+    }
+}
+
+#[async_trait]
+impl<A, P, S> EventStore<A> for MirroredEventStore<P, S>
+where
+    A: Aggregate + 'static,
+    P: EventStore<A> + 'static,
+    S: EventStore<A> + 'static,
+{
+    type AC = P::AC;
+
+    async fn load(&self, aggregate_id: &str) -> Vec<EventEnvelope<A>> {
+        self.primary.load(aggregate_id).await
+    }
+
+    fn load_stream<'a>(&'a self, aggregate_id: &'a str) -> EventEnvelopeStream<'a, A>
+    where
+        A: 'a,
+    {
+        self.primary.load_stream(aggregate_id)
+    }
+
+    async fn load_aggregate(&self, aggregate_id: &str) -> Self::AC {
+        self.primary.load_aggregate(aggregate_id).await
+    }
+
+    async fn last_event_version(&self, aggregate_id: &str) -> Option<usize> {
+        self.primary.last_event_version(aggregate_id).await
+    }
+
+    async fn is_duplicate_command(&self, aggregate_id: &str, command_id: &str) -> bool {
+        self.primary
+            .is_duplicate_command(aggregate_id, command_id)
+            .await
+    }
+
+    async fn commit(
+        &self,
+        events: Vec<A::Event>,
+        context: Self::AC,
+        metadata: HashMap<String, String>,
+    ) -> Result<Vec<EventEnvelope<A>>, AggregateError> {
+        let mirrored_events = events.clone();
+        let committed_events = self
+            .primary
+            .commit(events, context, metadata.clone())
+            .await?;
+        let aggregate_id = match committed_events.first() {
+            Some(event) => event.aggregate_id.as_str(),
+            None => return Ok(committed_events),
+        };
+        let first_sequence = committed_events[0].sequence;
+        if let Err(err) = self
+            .mirror(aggregate_id, first_sequence, mirrored_events, metadata)
+            .await
+        {
+            if self.propagate_secondary_failures {
+                return Err(err);
+            }
+            warn!(
+                aggregate_type = A::aggregate_type(),
+                aggregate_id,
+                error = %err,
+                "failed to commit events to secondary store"
+            );
+        }
+        Ok(committed_events)
+    }
+}
+
+impl<P, S> MirroredEventStore<P, S> {
+    // Commits the events to the secondary store, only if they will take the same sequence numbers
+    // as in the primary store.
+    async fn mirror<A>(
+        &self,
+        aggregate_id: &str,
+        first_sequence: usize,
+        events: Vec<A::Event>,
+        metadata: HashMap<String, String>,
+    ) -> Result<(), AggregateError>
+    where
+        A: Aggregate,
+        S: EventStore<A>,
+    {
+        let secondary_context = self.secondary.load_aggregate(aggregate_id).await;
+        let secondary_sequence = match secondary_context.current_sequence() {
+            Some(sequence) => sequence,
+            None => self
+                .secondary
+                .last_event_version(aggregate_id)
+                .await
+                .unwrap_or(0),
+        };
+        if secondary_sequence + 1 != first_sequence {
+            return Err(AggregateError::TechnicalError(format!(
+                "secondary store is out of sync for aggregate '{}', expected sequence {} but found {}",
+                aggregate_id,
+                first_sequence - 1,
+                secondary_sequence
+            )));
+        }
+        self.secondary
+            .commit(events, secondary_context, metadata)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<A, P, S> CountableEventStore<A> for MirroredEventStore<P, S>
+where
+    A: Aggregate + 'static,
+    P: CountableEventStore<A> + 'static,
+    S: EventStore<A> + 'static,
+{
+    async fn count_events(&self) -> usize {
+        self.primary.count_events().await
+    }
+}
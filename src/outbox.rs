@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+
+use crate::event::{DomainEvent, EventEnvelope};
+use crate::{Aggregate, AggregateError, CountableEventStore, EventEnvelopeStream, EventStore};
+
+/// A destination for committed events that must be reliably published, usually a table within
+/// the same database as the events themselves that is then read by a separate publisher.
+#[async_trait]
+pub trait OutboxStore<A>: Send + Sync
+where
+    A: Aggregate,
+{
+    /// Records the committed events in the outbox.
+    async fn write_to_outbox(&self, events: &[EventEnvelope<A>]) -> Result<(), OutboxError>;
+}
+
+/// The error returned when events could not be written to an `OutboxStore`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutboxError {
+    /// A description of the failure.
+    pub message: String,
+}
+
+impl OutboxError {
+    /// Creates a new error with the provided description.
+    pub fn new(message: &str) -> Self {
+        OutboxError {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl error::Error for OutboxError {}
+
+impl fmt::Display for OutboxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "outbox error: {}", self.message)
+    }
+}
+
+impl From<OutboxError> for AggregateError {
+    fn from(err: OutboxError) -> Self {
+        AggregateError::TechnicalError(err.to_string())
+    }
+}
+
+/// An event store wrapper that writes every batch of committed events to an `OutboxStore`,
+/// omitting any events that are
+/// [internal to the bounded context](trait.DomainEvent.html#method.is_internal).
+///
+/// The outbox is written immediately after the events are committed to the wrapped store, the
+/// wrapped store is not required to support transactions. If the outbox write fails an
+/// `AggregateError::TechnicalError` is returned, note that the events will have already been
+/// committed to the wrapped store.
+///
+/// ```
+/// # use cqrs_es::doc::MyAggregate;
+/// use cqrs_es::{CqrsFramework, MemOutboxStore, OutboxEventStore};
+/// use cqrs_es::mem_store::MemStore;
+///
+/// let outbox = MemOutboxStore::<MyAggregate>::default();
+/// let store = OutboxEventStore::new(MemStore::<MyAggregate>::default(), outbox);
+/// let cqrs = CqrsFramework::new(store, vec![]);
+/// ```
+pub struct OutboxEventStore<ES, O> {
+    store: ES,
+    outbox: O,
+}
+
+impl<ES, O> OutboxEventStore<ES, O> {
+    /// Wraps the provided event store, writing all committed events to the outbox.
+    pub fn new(store: ES, outbox: O) -> Self {
+        OutboxEventStore { store, outbox }
+    }
+}
+
+#[async_trait]
+impl<A, ES, O> EventStore<A> for OutboxEventStore<ES, O>
+where
+    A: Aggregate + 'static,
+    ES: EventStore<A> + 'static,
+    O: OutboxStore<A>,
+{
+    type AC = ES::AC;
+
+    async fn load(&self, aggregate_id: &str) -> Vec<EventEnvelope<A>> {
+        self.store.load(aggregate_id).await
+    }
+
+    fn load_stream<'a>(&'a self, aggregate_id: &'a str) -> EventEnvelopeStream<'a, A>
+    where
+        A: 'a,
+    {
+        self.store.load_stream(aggregate_id)
+    }
+
+    async fn load_aggregate(&self, aggregate_id: &str) -> Self::AC {
+        self.store.load_aggregate(aggregate_id).await
+    }
+
+    async fn last_event_version(&self, aggregate_id: &str) -> Option<usize> {
+        self.store.last_event_version(aggregate_id).await
+    }
+
+    async fn is_duplicate_command(&self, aggregate_id: &str, command_id: &str) -> bool {
+        self.store
+            .is_duplicate_command(aggregate_id, command_id)
+            .await
+    }
+
+    async fn commit(
+        &self,
+        events: Vec<A::Event>,
+        context: Self::AC,
+        metadata: HashMap<String, String>,
+    ) -> Result<Vec<EventEnvelope<A>>, AggregateError> {
+        let committed_events = self.store.commit(events, context, metadata).await?;
+        let public_events: Vec<EventEnvelope<A>> = committed_events
+            .iter()
+            .filter(|envelope| !envelope.payload.is_internal())
+            .cloned()
+            .collect();
+        if !public_events.is_empty() {
+            self.outbox.write_to_outbox(&public_events).await?;
+        }
+        Ok(committed_events)
+    }
+}
+
+#[async_trait]
+impl<A, ES, O> CountableEventStore<A> for OutboxEventStore<ES, O>
+where
+    A: Aggregate + 'static,
+    ES: CountableEventStore<A> + 'static,
+    O: OutboxStore<A>,
+{
+    async fn count_events(&self) -> usize {
+        self.store.count_events().await
+    }
+}
+
+/// Simple in-memory outbox useful for verifying the events written to an outbox in tests.
+///
+/// ```
+/// # use cqrs_es::doc::MyAggregate;
+/// use cqrs_es::MemOutboxStore;
+///
+/// let outbox = MemOutboxStore::<MyAggregate>::default();
+/// //...
+/// let outbox_events = outbox.get_events();
+/// for event in outbox_events.read().unwrap().iter() {
+///     println!("{:?}", event);
+/// }
+/// ```
+pub struct MemOutboxStore<A: Aggregate> {
+    events: Arc<RwLock<Vec<EventEnvelope<A>>>>,
+}
+
+impl<A: Aggregate> Default for MemOutboxStore<A> {
+    fn default() -> Self {
+        let events = Default::default();
+        MemOutboxStore { events }
+    }
+}
+
+impl<A: Aggregate> Clone for MemOutboxStore<A> {
+    fn clone(&self) -> Self {
+        MemOutboxStore {
+            events: Arc::clone(&self.events),
+        }
+    }
+}
+
+impl<A: Aggregate> MemOutboxStore<A> {
+    /// Get a shared copy of the events written to the outbox, in the order they were written.
+    pub fn get_events(&self) -> Arc<RwLock<Vec<EventEnvelope<A>>>> {
+        Arc::clone(&self.events)
+    }
+}
+
+#[async_trait]
+impl<A: Aggregate> OutboxStore<A> for MemOutboxStore<A> {
+    async fn write_to_outbox(&self, events: &[EventEnvelope<A>]) -> Result<(), OutboxError> {
+        // uninteresting unwrap: this is not a struct for production use
+        let mut outbox = self.events.write().unwrap();
+        outbox.extend_from_slice(events);
+        Ok(())
+    }
+}
@@ -1,8 +1,11 @@
 use async_trait::async_trait;
+use std::any::Any;
 use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
 
 use serde::de::DeserializeOwned;
 use serde::Serialize;
+use tokio::task::JoinHandle;
 
 use crate::aggregate::Aggregate;
 use crate::event::EventEnvelope;
@@ -15,6 +18,26 @@ pub trait Query<A: Aggregate>: Send + Sync {
     /// Events will be dispatched here immediately after being committed for the downstream queries
     /// to be updated.
     async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<A>]);
+    /// Indicates whether this query is interested in events from all instances of the aggregate,
+    /// by default this is `true`.
+    ///
+    /// A query that returns `false` should override `subscribes_to` to select the aggregate
+    /// instances that it is interested in.
+    fn subscribe_to_all(&self) -> bool {
+        true
+    }
+    /// Indicates whether events for the given aggregate instance should be dispatched to this
+    /// query. By default this follows `subscribe_to_all`.
+    ///
+    /// Override this to subscribe to a subset of aggregate instances, e.g., using a prefix or
+    /// pattern on the aggregate ID.
+    fn subscribes_to(&self, _aggregate_id: &str) -> bool {
+        self.subscribe_to_all()
+    }
+    /// A name identifying this query in logs and metrics, by default the name of the type.
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
 }
 
 /// A `Query` is a read element in a CQRS system. As events are emitted multiple downstream queries
@@ -28,3 +51,271 @@ pub trait View<A: Aggregate>: Debug + Default + Serialize + DeserializeOwned + S
     /// this method.
     fn update(&mut self, event: &EventEnvelope<A>);
 }
+
+/// A wrapper for a `Query` where eventual consistency is acceptable. Rather than blocking the
+/// command path, each dispatch is performed on a background `tokio` task.
+///
+/// Note that events for the same aggregate instance may be delivered to the wrapped query out of
+/// order, the wrapped query should not rely on the order of dispatches.
+///
+/// ```
+/// # use cqrs_es::doc::MyAggregate;
+/// # use cqrs_es::{EventEnvelope, Query};
+/// # use async_trait::async_trait;
+/// use std::sync::Arc;
+/// use cqrs_es::{CqrsFramework, WeakConsistencyQuery};
+/// use cqrs_es::mem_store::MemStore;
+///
+/// # struct MyQuery;
+/// # #[async_trait]
+/// # impl Query<MyAggregate> for MyQuery {
+/// #     async fn dispatch(&self, _aggregate_id: &str, _events: &[EventEnvelope<MyAggregate>]) {}
+/// # }
+/// let query = Arc::new(WeakConsistencyQuery::new(MyQuery));
+/// let store = MemStore::<MyAggregate>::default();
+/// let cqrs = CqrsFramework::new(store, vec![query.clone()]);
+/// ```
+pub struct WeakConsistencyQuery<Q> {
+    query: Arc<Q>,
+    pending: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl<Q> WeakConsistencyQuery<Q> {
+    /// Wraps the provided query so that its dispatches are performed in the background.
+    pub fn new(query: Q) -> Self {
+        WeakConsistencyQuery {
+            query: Arc::new(query),
+            pending: Default::default(),
+        }
+    }
+    /// Waits for all pending dispatches to complete, this is primarily useful in tests.
+    pub async fn flush(&self) {
+        let pending: Vec<JoinHandle<()>> = {
+            // uninteresting unwrap: the lock is never held across a panic
+            let mut pending = self.pending.lock().unwrap();
+            pending.drain(..).collect()
+        };
+        for task in pending {
+            // a dispatch that panicked has no result to report
+            let _ = task.await;
+        }
+    }
+}
+
+#[async_trait]
+impl<A, Q> Query<A> for WeakConsistencyQuery<Q>
+where
+    A: Aggregate + 'static,
+    Q: Query<A> + 'static,
+{
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<A>]) {
+        let query = Arc::clone(&self.query);
+        let aggregate_id = aggregate_id.to_string();
+        let events = events.to_vec();
+        let task = tokio::spawn(async move {
+            query.dispatch(&aggregate_id, &events).await;
+        });
+        // uninteresting unwrap: the lock is never held across a panic
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|task| !task.is_finished());
+        pending.push(task);
+    }
+
+    fn subscribe_to_all(&self) -> bool {
+        self.query.subscribe_to_all()
+    }
+
+    fn subscribes_to(&self, aggregate_id: &str) -> bool {
+        self.query.subscribes_to(aggregate_id)
+    }
+
+    fn name(&self) -> &'static str {
+        self.query.name()
+    }
+}
+
+pub(crate) type EventPredicate<A> = Box<dyn Fn(&EventEnvelope<A>) -> bool + Send + Sync>;
+
+/// A wrapper for a `Query` that only dispatches the events matching a predicate, keeping the
+/// wrapped query focused on the events it is interested in. If no events in a commit match, the
+/// wrapped query is not called.
+///
+/// ```
+/// # use cqrs_es::doc::{Customer, CustomerEvent};
+/// # use cqrs_es::{EventEnvelope, Query};
+/// # use async_trait::async_trait;
+/// use std::sync::Arc;
+/// use cqrs_es::{CqrsFramework, FilteredQuery};
+/// use cqrs_es::mem_store::MemStore;
+///
+/// # struct CustomerNameQuery;
+/// # #[async_trait]
+/// # impl Query<Customer> for CustomerNameQuery {
+/// #     async fn dispatch(&self, _aggregate_id: &str, _events: &[EventEnvelope<Customer>]) {}
+/// # }
+/// let query = FilteredQuery::new(CustomerNameQuery, |event: &EventEnvelope<Customer>| {
+///     matches!(event.payload, CustomerEvent::NameAdded { .. })
+/// });
+/// let store = MemStore::<Customer>::default();
+/// let cqrs = CqrsFramework::new(store, vec![Arc::new(query)]);
+/// ```
+pub struct FilteredQuery<A, Q>
+where
+    A: Aggregate,
+{
+    query: Q,
+    predicate: EventPredicate<A>,
+}
+
+impl<A, Q> FilteredQuery<A, Q>
+where
+    A: Aggregate,
+{
+    /// Wraps the provided query so that only events matching the predicate are dispatched to it.
+    pub fn new<F>(query: Q, predicate: F) -> Self
+    where
+        F: Fn(&EventEnvelope<A>) -> bool + Send + Sync + 'static,
+    {
+        FilteredQuery {
+            query,
+            predicate: Box::new(predicate),
+        }
+    }
+}
+
+#[async_trait]
+impl<A, Q> Query<A> for FilteredQuery<A, Q>
+where
+    A: Aggregate,
+    Q: Query<A>,
+{
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<A>]) {
+        let filtered_events: Vec<EventEnvelope<A>> = events
+            .iter()
+            .filter(|event| (self.predicate)(event))
+            .cloned()
+            .collect();
+        if filtered_events.is_empty() {
+            return;
+        }
+        self.query.dispatch(aggregate_id, &filtered_events).await;
+    }
+
+    fn subscribe_to_all(&self) -> bool {
+        self.query.subscribe_to_all()
+    }
+
+    fn subscribes_to(&self, aggregate_id: &str) -> bool {
+        self.query.subscribes_to(aggregate_id)
+    }
+
+    fn name(&self) -> &'static str {
+        self.query.name()
+    }
+}
+
+/// A type-erased query that may receive events from any number of aggregate types, e.g., a
+/// dashboard combining events from both `Order` and `Customer` aggregates into a single view.
+///
+/// Each of the dispatched events is an `EventEnvelope` of the aggregate identified by
+/// `aggregate_type`, use [`downcast_events`] to recover the typed envelopes. To receive events an
+/// `ErasedQuery` should be wrapped in a [`MultiAggregateQuery`].
+#[async_trait]
+pub trait ErasedQuery: Send + Sync {
+    /// Events will be dispatched here immediately after being committed, along with the type of
+    /// the aggregate that produced them.
+    async fn dispatch_erased(
+        &self,
+        aggregate_type: &str,
+        aggregate_id: &str,
+        events: &[Box<dyn Any + Send + Sync>],
+    );
+    /// A name identifying this query in logs and metrics, by default the name of the type.
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// Recovers the typed event envelopes from events dispatched to an `ErasedQuery`, any events
+/// belonging to a different aggregate are skipped.
+///
+/// ```
+/// # use cqrs_es::doc::Customer;
+/// use std::any::Any;
+/// use cqrs_es::{downcast_events, EventEnvelope};
+///
+/// fn customer_events(events: &[Box<dyn Any + Send + Sync>]) -> Vec<&EventEnvelope<Customer>> {
+///     downcast_events::<Customer>(events)
+/// }
+/// ```
+pub fn downcast_events<A>(events: &[Box<dyn Any + Send + Sync>]) -> Vec<&EventEnvelope<A>>
+where
+    A: Aggregate + 'static,
+{
+    events
+        .iter()
+        .filter_map(|event| event.downcast_ref::<EventEnvelope<A>>())
+        .collect()
+}
+
+/// Dispatches events from multiple aggregate types to a single `ErasedQuery`. A
+/// `MultiAggregateQuery` implements `Query` for every aggregate type, so the same instance may be
+/// registered with a `CqrsFramework` for each of the aggregates.
+///
+/// ```
+/// # use cqrs_es::doc::{Customer, MyAggregate};
+/// # use async_trait::async_trait;
+/// use std::any::Any;
+/// use std::sync::Arc;
+/// use cqrs_es::{CqrsFramework, ErasedQuery, MultiAggregateQuery};
+/// use cqrs_es::mem_store::MemStore;
+///
+/// struct DashboardQuery;
+///
+/// #[async_trait]
+/// impl ErasedQuery for DashboardQuery {
+///     async fn dispatch_erased(
+///         &self,
+///         aggregate_type: &str,
+///         aggregate_id: &str,
+///         events: &[Box<dyn Any + Send + Sync>],
+///     ) {
+///         println!("{} events for {} '{}'", events.len(), aggregate_type, aggregate_id);
+///     }
+/// }
+///
+/// let query = Arc::new(MultiAggregateQuery::new(DashboardQuery));
+/// let customer_cqrs = CqrsFramework::new(MemStore::<Customer>::default(), vec![query.clone()]);
+/// let my_cqrs = CqrsFramework::new(MemStore::<MyAggregate>::default(), vec![query]);
+/// ```
+pub struct MultiAggregateQuery<Q> {
+    query: Q,
+}
+
+impl<Q> MultiAggregateQuery<Q> {
+    /// Wraps the provided query so that it may receive events from multiple aggregate types.
+    pub fn new(query: Q) -> Self {
+        MultiAggregateQuery { query }
+    }
+}
+
+#[async_trait]
+impl<A, Q> Query<A> for MultiAggregateQuery<Q>
+where
+    A: Aggregate + 'static,
+    Q: ErasedQuery,
+{
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<A>]) {
+        let erased_events: Vec<Box<dyn Any + Send + Sync>> = events
+            .iter()
+            .map(|event| Box::new(event.clone()) as Box<dyn Any + Send + Sync>)
+            .collect();
+        self.query
+            .dispatch_erased(A::aggregate_type(), aggregate_id, &erased_events)
+            .await;
+    }
+
+    fn name(&self) -> &'static str {
+        self.query.name()
+    }
+}
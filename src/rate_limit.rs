@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::AggregateError;
+
+/// Limits the rate at which commands may be applied to any single aggregate instance, protecting
+/// an aggregate from being flooded with commands.
+///
+/// Each aggregate ID has a token bucket holding up to `burst` tokens that is replenished with a
+/// single token every `replenish_interval`. Each command consumes a token, a command for an
+/// aggregate with an empty bucket is rejected with a `UserError` with the code
+/// `"RATE_LIMIT_EXCEEDED"`, the client should retry the command later. Add a rate limiter to a
+/// framework with
+/// [`CqrsFramework::with_rate_limiter`](struct.CqrsFramework.html#method.with_rate_limiter).
+///
+/// ```
+/// use std::time::Duration;
+/// use cqrs_es::CommandRateLimiter;
+///
+/// let limiter = CommandRateLimiter::new(2, Duration::from_secs(1));
+/// assert!(limiter.check("agg-id-F39A0C").is_ok());
+/// assert!(limiter.check("agg-id-F39A0C").is_ok());
+/// assert!(limiter.check("agg-id-F39A0C").is_err());
+/// assert!(limiter.check("agg-id-74E2B1").is_ok());
+/// ```
+pub struct CommandRateLimiter {
+    burst: u32,
+    replenish_interval: Duration,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+struct TokenBucket {
+    tokens: u32,
+    replenished_at: Instant,
+}
+
+impl CommandRateLimiter {
+    /// Creates a rate limiter allowing bursts of up to `burst` commands per aggregate, with one
+    /// further command allowed every `replenish_interval`.
+    pub fn new(burst: u32, replenish_interval: Duration) -> Self {
+        CommandRateLimiter {
+            burst,
+            replenish_interval,
+            buckets: Default::default(),
+        }
+    }
+    /// Consumes a token for the aggregate, returning an error if none remain.
+    pub fn check(&self, aggregate_id: &str) -> Result<(), AggregateError> {
+        let now = Instant::now();
+        // uninteresting unwrap: the lock is never held across a panic
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets
+            .entry(aggregate_id.to_string())
+            .or_insert_with(|| TokenBucket {
+                tokens: self.burst,
+                replenished_at: now,
+            });
+        let elapsed = now.duration_since(bucket.replenished_at);
+        let replenished = elapsed
+            .as_nanos()
+            .checked_div(self.replenish_interval.as_nanos())
+            .unwrap_or(u128::MAX);
+        if replenished >= u128::from(self.burst) {
+            bucket.tokens = self.burst;
+            bucket.replenished_at = now;
+        } else if replenished > 0 {
+            // lossless: replenished is less than the burst
+            let replenished = replenished as u32;
+            bucket.tokens = (bucket.tokens + replenished).min(self.burst);
+            bucket.replenished_at += self.replenish_interval * replenished;
+        }
+        if bucket.tokens == 0 {
+            return Err(AggregateError::new_with_code(
+                "rate limit exceeded",
+                "RATE_LIMIT_EXCEEDED",
+            ));
+        }
+        bucket.tokens -= 1;
+        Ok(())
+    }
+}
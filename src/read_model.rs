@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Persistence for the state of the views maintained by a projection, keyed by view ID.
+///
+/// ```ignore
+/// #[async_trait]
+/// impl Query<Customer> for CustomerProjection {
+///     async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<Customer>]) {
+///         let mut view = self.views.load(aggregate_id).await.unwrap_or_default();
+///         for event in events {
+///             view.update(event);
+///         }
+///         self.views.save(aggregate_id, &view).await;
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait ReadModelStore<V>: Send + Sync
+where
+    V: Serialize + DeserializeOwned + Send + Sync,
+{
+    /// Saves the view, replacing any view previously saved with the same ID.
+    async fn save(&self, id: &str, view: &V);
+    /// Loads the view with the given ID, or `None` if no view has been saved.
+    async fn load(&self, id: &str) -> Option<V>;
+    /// Deletes the view with the given ID, if any.
+    async fn delete(&self, id: &str);
+}
+
+/// Simple in-memory read model store useful for application development and testing purposes.
+/// Clones of the store share the same views.
+///
+/// ```
+/// # use cqrs_es::doc::Customer;
+/// use cqrs_es::MemReadModelStore;
+///
+/// let store = MemReadModelStore::<Customer>::default();
+/// //...
+/// let views = store.get_views();
+/// for (id, view) in views.read().unwrap().iter() {
+///     println!("{}: {}", id, view.name);
+/// }
+/// ```
+pub struct MemReadModelStore<V> {
+    views: Arc<RwLock<HashMap<String, V>>>,
+}
+
+impl<V> Default for MemReadModelStore<V> {
+    fn default() -> Self {
+        let views = Default::default();
+        MemReadModelStore { views }
+    }
+}
+
+impl<V> Clone for MemReadModelStore<V> {
+    fn clone(&self) -> Self {
+        MemReadModelStore {
+            views: Arc::clone(&self.views),
+        }
+    }
+}
+
+impl<V> MemReadModelStore<V> {
+    /// Get a shared copy of the saved views, keyed by view ID.
+    pub fn get_views(&self) -> Arc<RwLock<HashMap<String, V>>> {
+        Arc::clone(&self.views)
+    }
+}
+
+#[async_trait]
+impl<V> ReadModelStore<V> for MemReadModelStore<V>
+where
+    V: Serialize + DeserializeOwned + Clone + Send + Sync,
+{
+    async fn save(&self, id: &str, view: &V) {
+        // uninteresting unwrap: this will not be used in production, for tests only
+        let mut views = self.views.write().unwrap();
+        views.insert(id.to_string(), view.clone());
+    }
+
+    async fn load(&self, id: &str) -> Option<V> {
+        // uninteresting unwrap: this will not be used in production, for tests only
+        let views = self.views.read().unwrap();
+        views.get(id).cloned()
+    }
+
+    async fn delete(&self, id: &str) {
+        // uninteresting unwrap: this will not be used in production, for tests only
+        let mut views = self.views.write().unwrap();
+        views.remove(id);
+    }
+}
@@ -0,0 +1,104 @@
+use std::error;
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::{AggregateError, DomainEvent};
+
+/// Converts events to and from their persisted form, allowing an event store to use a format
+/// other than JSON, e.g., MessagePack, Protobuf or Avro.
+///
+/// The event type and version are provided on deserialization so that an implementation may
+/// upcast events persisted in an older form.
+pub trait EventSerializer<E>: Send + Sync
+where
+    E: DomainEvent,
+{
+    /// Serializes the event payload.
+    fn serialize(&self, event: &E) -> Result<Vec<u8>, SerializationError>;
+    /// Deserializes an event payload of the given event type and version.
+    fn deserialize(
+        &self,
+        bytes: &[u8],
+        event_type: &str,
+        event_version: &str,
+    ) -> Result<E, SerializationError>;
+}
+
+/// The error returned when an event could not be serialized or deserialized by an
+/// `EventSerializer`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerializationError {
+    /// A description of the failure.
+    pub message: String,
+}
+
+impl SerializationError {
+    /// Creates a new error with the provided description.
+    pub fn new(message: &str) -> Self {
+        SerializationError {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl error::Error for SerializationError {}
+
+impl fmt::Display for SerializationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "serialization error: {}", self.message)
+    }
+}
+
+impl From<serde_json::Error> for SerializationError {
+    fn from(err: serde_json::Error) -> Self {
+        SerializationError::new(&err.to_string())
+    }
+}
+
+impl From<SerializationError> for AggregateError {
+    fn from(err: SerializationError) -> Self {
+        AggregateError::TechnicalError(err.to_string())
+    }
+}
+
+/// The default `EventSerializer`, serializing events to JSON using `serde_json`.
+///
+/// ```
+/// # use cqrs_es::doc::CustomerEvent;
+/// use cqrs_es::{EventSerializer, JsonEventSerializer};
+///
+/// let serializer = JsonEventSerializer::default();
+/// let event = CustomerEvent::NameAdded { changed_name: "John Doe".to_string() };
+/// let bytes = serializer.serialize(&event).unwrap();
+/// let deserialized = serializer.deserialize(&bytes, "NameAdded", "1.0").unwrap();
+/// assert_eq!(event, deserialized);
+/// ```
+pub struct JsonEventSerializer<E> {
+    _phantom: PhantomData<fn() -> E>,
+}
+
+impl<E> Default for JsonEventSerializer<E> {
+    fn default() -> Self {
+        JsonEventSerializer {
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<E> EventSerializer<E> for JsonEventSerializer<E>
+where
+    E: DomainEvent,
+{
+    fn serialize(&self, event: &E) -> Result<Vec<u8>, SerializationError> {
+        Ok(serde_json::to_vec(event)?)
+    }
+
+    fn deserialize(
+        &self,
+        bytes: &[u8],
+        _event_type: &str,
+        _event_version: &str,
+    ) -> Result<E, SerializationError> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
@@ -0,0 +1,88 @@
+use std::future::{Future, IntoFuture};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::Notify;
+
+use crate::AggregateError;
+
+// Tracks the commands currently being processed by a `CqrsFramework`.
+#[derive(Default)]
+pub(crate) struct InFlightCommands {
+    count: AtomicUsize,
+    shutting_down: AtomicBool,
+    drained: Notify,
+}
+
+impl InFlightCommands {
+    // Registers the start of a command, the returned guard marks its completion when dropped.
+    // Fails if shutdown has been signaled.
+    pub(crate) fn start(self: &Arc<Self>) -> Result<InFlightGuard, AggregateError> {
+        self.count.fetch_add(1, Ordering::SeqCst);
+        let guard = InFlightGuard {
+            commands: self.clone(),
+        };
+        if self.shutting_down.load(Ordering::SeqCst) {
+            return Err(AggregateError::TechnicalError(
+                "framework is shutting down".to_string(),
+            ));
+        }
+        Ok(guard)
+    }
+
+    pub(crate) fn shutdown(self: &Arc<Self>) -> ShutdownHandle {
+        self.shutting_down.store(true, Ordering::SeqCst);
+        ShutdownHandle {
+            commands: self.clone(),
+        }
+    }
+}
+
+pub(crate) struct InFlightGuard {
+    commands: Arc<InFlightCommands>,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if self.commands.count.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.commands.drained.notify_waiters();
+        }
+    }
+}
+
+/// Returned by [`CqrsFramework::shutdown_signal`](struct.CqrsFramework.html#method.shutdown_signal),
+/// awaiting the handle waits until all commands that were in flight when shutdown was signaled
+/// have completed.
+///
+/// ```ignore
+/// let handle = cqrs.shutdown_signal();
+/// tokio::time::timeout(Duration::from_secs(10), handle).await?;
+/// ```
+pub struct ShutdownHandle {
+    commands: Arc<InFlightCommands>,
+}
+
+impl ShutdownHandle {
+    /// The number of commands that are still being processed.
+    pub fn in_flight(&self) -> usize {
+        self.commands.count.load(Ordering::SeqCst)
+    }
+}
+
+impl IntoFuture for ShutdownHandle {
+    type Output = ();
+    type IntoFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(async move {
+            loop {
+                let drained = self.commands.drained.notified();
+                if self.in_flight() == 0 {
+                    return;
+                }
+                drained.await;
+            }
+        })
+    }
+}
@@ -0,0 +1,117 @@
+use std::marker::PhantomData;
+
+use serde::{Deserialize, Serialize};
+
+use crate::aggregate::Aggregate;
+
+/// An `Aggregate` that tracks the version of its own serialized schema.
+///
+/// The schema version should be incremented whenever a change to the aggregate would prevent a
+/// previously serialized state from being correctly deserialized. This allows a stale snapshot to
+/// be detected, the aggregate should then be rebuilt by replaying all of its events.
+///
+/// ```ignore
+/// impl VersionedAggregate for Customer {
+///     fn schema_version() -> u32 {
+///         2
+///     }
+/// }
+/// ```
+pub trait VersionedAggregate: Aggregate {
+    /// The current version of the serialized schema of this aggregate.
+    fn schema_version() -> u32;
+}
+
+/// The serialized state of a `VersionedAggregate` along with the schema version used to
+/// serialize it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotEnvelope {
+    /// The schema version of the aggregate when the snapshot was taken.
+    pub schema_version: u32,
+    /// The serialized aggregate state.
+    pub payload: serde_json::Value,
+}
+
+impl SnapshotEnvelope {
+    /// Serializes the aggregate along with its current schema version.
+    pub fn new<A: VersionedAggregate>(aggregate: &A) -> Result<Self, serde_json::Error> {
+        Ok(SnapshotEnvelope {
+            schema_version: A::schema_version(),
+            payload: serde_json::to_value(aggregate)?,
+        })
+    }
+    /// Returns `true` if the snapshot was taken with the current schema version of the aggregate.
+    pub fn is_current<A: VersionedAggregate>(&self) -> bool {
+        self.schema_version == A::schema_version()
+    }
+    /// Deserializes the aggregate, returning `None` if the snapshot is stale or cannot be
+    /// deserialized. In either case the aggregate should be rebuilt from its events.
+    pub fn restore<A: VersionedAggregate>(&self) -> Option<A> {
+        if !self.is_current::<A>() {
+            return None;
+        }
+        serde_json::from_value(self.payload.clone()).ok()
+    }
+}
+
+/// The serialized state of an aggregate along with the version, the sequence number of the last
+/// event applied, at which it was taken.
+pub struct AggregateSnapshot<A: Aggregate> {
+    /// The sequence number of the last event applied to the aggregate.
+    pub version: usize,
+    /// The serialized aggregate state.
+    pub payload: serde_json::Value,
+    _phantom: PhantomData<A>,
+}
+
+/// Serializes the aggregate state at the provided version.
+///
+/// # Panics
+///
+/// Panics if the aggregate cannot be serialized, this is intended for use in tests.
+///
+/// ```
+/// use cqrs_es::doc::Customer;
+/// use cqrs_es::take_snapshot;
+///
+/// let snapshot = take_snapshot(&Customer::default(), 3);
+/// assert_eq!(3, snapshot.version);
+/// ```
+pub fn take_snapshot<A: Aggregate>(aggregate: &A, version: usize) -> AggregateSnapshot<A> {
+    let payload = match serde_json::to_value(aggregate) {
+        Ok(payload) => payload,
+        Err(err) => panic!("unable to serialize aggregate: {}", err),
+    };
+    AggregateSnapshot {
+        version,
+        payload,
+        _phantom: PhantomData,
+    }
+}
+
+/// Restores the aggregate from a snapshot, returning it along with the version at which the
+/// snapshot was taken.
+///
+/// # Panics
+///
+/// Panics if the aggregate cannot be deserialized, this is intended for use in tests.
+///
+/// ```
+/// use cqrs_es::doc::Customer;
+/// use cqrs_es::{restore_from_snapshot, take_snapshot};
+///
+/// let customer = Customer {
+///     customer_id: "cust-A".to_string(),
+///     name: "Bob".to_string(),
+///     email: "bob@example.com".to_string(),
+/// };
+/// let (restored, version) = restore_from_snapshot(take_snapshot(&customer, 3));
+/// assert_eq!("Bob", restored.name);
+/// assert_eq!(3, version);
+/// ```
+pub fn restore_from_snapshot<A: Aggregate>(snapshot: AggregateSnapshot<A>) -> (A, usize) {
+    match serde_json::from_value(snapshot.payload) {
+        Ok(aggregate) => (aggregate, snapshot.version),
+        Err(err) => panic!("unable to deserialize aggregate snapshot: {}", err),
+    }
+}
@@ -1,9 +1,17 @@
 use async_trait::async_trait;
+use futures::future::join_all;
+use futures::stream::{self, Stream, StreamExt};
 use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::Arc;
 
 use crate::aggregate::Aggregate;
 use crate::event::EventEnvelope;
-use crate::AggregateError;
+use crate::{AggregateError, HealthCheckError};
+
+/// A stream of events loaded from an `EventStore`, see
+/// [`EventStore::load_stream`](trait.EventStore.html#method.load_stream).
+pub type EventEnvelopeStream<'a, A> = Pin<Box<dyn Stream<Item = EventEnvelope<A>> + Send + 'a>>;
 
 /// The abstract central source for loading past events and committing new events.
 #[async_trait]
@@ -14,10 +22,21 @@ where
     /// Provides the current state of an aggregate along with surrounding context.
     /// This is used by the [CqrsFramework](struct.CqrsFramework.html) when loading
     /// an aggregate in order to handle incoming commands.
-    type AC: AggregateContext<A>;
+    type AC: AggregateContext<A> + Send;
 
     /// Load all events for a particular `aggregate_id`
     async fn load(&self, aggregate_id: &str) -> Vec<EventEnvelope<A>>;
+    /// Load all events for a particular `aggregate_id` as a stream, allowing the events to be
+    /// processed incrementally.
+    ///
+    /// The default implementation loads all events before streaming them, event stores should
+    /// override this to fetch events as the stream is consumed.
+    fn load_stream<'a>(&'a self, aggregate_id: &'a str) -> EventEnvelopeStream<'a, A>
+    where
+        A: 'a,
+    {
+        Box::pin(stream::once(self.load(aggregate_id)).flat_map(stream::iter))
+    }
     /// Load aggregate at current state
     async fn load_aggregate(&self, aggregate_id: &str) -> Self::AC;
     /// Commit new events
@@ -28,6 +47,30 @@ where
         metadata: HashMap<String, String>,
     ) -> Result<Vec<EventEnvelope<A>>, AggregateError>;
 
+    /// The sequence number of the most recent event for an aggregate instance, or `None` if no
+    /// events have been committed for it.
+    ///
+    /// The default implementation loads all events, event stores should override this with a
+    /// query that avoids fetching and deserializing the event payloads.
+    async fn last_event_version(&self, aggregate_id: &str) -> Option<usize> {
+        self.load(aggregate_id)
+            .await
+            .last()
+            .map(|envelope| envelope.sequence)
+    }
+
+    /// Returns `true` if a command with the command ID, as held in the metadata under the
+    /// [`COMMAND_ID_METADATA_KEY`](constant.COMMAND_ID_METADATA_KEY.html), has already been
+    /// committed for the aggregate instance. The `CqrsFramework` checks this before handling a
+    /// command, a duplicate command is neither handled nor dispatched to any query.
+    ///
+    /// The default does not record command IDs and returns `false`. Event stores that record
+    /// command IDs should override this, and should also reject the commit of a duplicate command
+    /// that is not caught by this check with an `AggregateError::DuplicateCommand`.
+    async fn is_duplicate_command(&self, _aggregate_id: &str, _command_id: &str) -> bool {
+        false
+    }
+
     /// Method to wrap a set of events with the additional metadata needed for persistence and publishing
     fn wrap_events(
         &self,
@@ -36,13 +79,10 @@ where
         resultant_events: Vec<A::Event>,
         base_metadata: HashMap<String, String>,
     ) -> Vec<EventEnvelope<A>> {
-        let mut sequence = current_sequence;
         let mut wrapped_events: Vec<EventEnvelope<A>> = Vec::new();
-        for payload in resultant_events {
-            sequence += 1;
+        for (sequence, payload) in (current_sequence + 1..).zip(resultant_events) {
             let aggregate_type = A::aggregate_type().to_string();
             let aggregate_id: String = aggregate_id.to_string();
-            let sequence = sequence;
             let metadata = base_metadata.clone();
             wrapped_events.push(EventEnvelope::new_with_metadata(
                 aggregate_id,
@@ -56,6 +96,195 @@ where
     }
 }
 
+/// A shared event store, allowing e.g. an `Arc<MemStore<A>>` to be used directly by a
+/// `CqrsFramework` while the store is also held elsewhere.
+#[async_trait]
+impl<A, ES> EventStore<A> for Arc<ES>
+where
+    A: Aggregate + 'static,
+    ES: EventStore<A> + 'static,
+{
+    type AC = ES::AC;
+
+    async fn load(&self, aggregate_id: &str) -> Vec<EventEnvelope<A>> {
+        self.as_ref().load(aggregate_id).await
+    }
+
+    fn load_stream<'a>(&'a self, aggregate_id: &'a str) -> EventEnvelopeStream<'a, A>
+    where
+        A: 'a,
+    {
+        self.as_ref().load_stream(aggregate_id)
+    }
+
+    async fn load_aggregate(&self, aggregate_id: &str) -> Self::AC {
+        self.as_ref().load_aggregate(aggregate_id).await
+    }
+
+    async fn commit(
+        &self,
+        events: Vec<A::Event>,
+        context: Self::AC,
+        metadata: HashMap<String, String>,
+    ) -> Result<Vec<EventEnvelope<A>>, AggregateError> {
+        self.as_ref().commit(events, context, metadata).await
+    }
+
+    async fn last_event_version(&self, aggregate_id: &str) -> Option<usize> {
+        self.as_ref().last_event_version(aggregate_id).await
+    }
+
+    async fn is_duplicate_command(&self, aggregate_id: &str, command_id: &str) -> bool {
+        self.as_ref()
+            .is_duplicate_command(aggregate_id, command_id)
+            .await
+    }
+}
+
+/// An extension to `EventStore` for loading the events of an aggregate instance a page at a time,
+/// for use by tools that render the history of aggregates with very large event streams.
+#[async_trait]
+pub trait PaginatedEventStore<A>: EventStore<A>
+where
+    A: Aggregate,
+{
+    /// Load a single page of events for a particular `aggregate_id`, along with the total number
+    /// of events for that aggregate instance. Pages are numbered from zero.
+    async fn load_page(
+        &self,
+        aggregate_id: &str,
+        page: usize,
+        page_size: usize,
+    ) -> (Vec<EventEnvelope<A>>, usize);
+}
+
+/// An extension to `EventStore` for counting the events held by the store, for use by
+/// operational tooling and metrics rather than by the framework.
+#[async_trait]
+pub trait CountableEventStore<A>: EventStore<A>
+where
+    A: Aggregate,
+{
+    /// The total number of events committed across all aggregate instances.
+    async fn count_events(&self) -> usize;
+}
+
+#[async_trait]
+impl<A, ES> CountableEventStore<A> for Arc<ES>
+where
+    A: Aggregate + 'static,
+    ES: CountableEventStore<A> + 'static,
+{
+    async fn count_events(&self) -> usize {
+        self.as_ref().count_events().await
+    }
+}
+
+/// An extension to `EventStore` for loading the events of many aggregate instances in a single
+/// call. The default implementation loads each aggregate instance concurrently, event stores that
+/// support it should override this with a single batched query.
+///
+/// ```ignore
+/// impl BulkEventStore<Customer> for CustomerEventStore {}
+///
+/// let events = store.load_many(&["cust-A", "cust-B"]).await;
+/// ```
+#[async_trait]
+pub trait BulkEventStore<A>: EventStore<A>
+where
+    A: Aggregate,
+{
+    /// Load all events for each of the `aggregate_ids`, keyed by aggregate ID. Every requested
+    /// aggregate ID is present in the result, with no events if none have been committed.
+    async fn load_many(&self, aggregate_ids: &[&str]) -> HashMap<String, Vec<EventEnvelope<A>>> {
+        let loads = aggregate_ids.iter().map(|aggregate_id| async move {
+            (aggregate_id.to_string(), self.load(aggregate_id).await)
+        });
+        join_all(loads).await.into_iter().collect()
+    }
+}
+
+/// An extension to `EventStore` for backends supporting transactions that span the commits of
+/// multiple aggregate instances, allowing e.g. a saga to commit the events of several aggregates
+/// atomically.
+///
+/// Events are committed within a transaction with `commit_in_transaction`, they are not visible
+/// to any load until the transaction is committed with `commit_transaction`. A transaction that
+/// is rolled back, or that fails to commit, leaves the store unchanged.
+///
+/// ```ignore
+/// let mut tx = store.begin().await;
+/// let from = store.load_aggregate("account-A").await;
+/// store.commit_in_transaction(&mut tx, vec![withdrawn], from, metadata.clone()).await?;
+/// let to = store.load_aggregate("account-B").await;
+/// store.commit_in_transaction(&mut tx, vec![deposited], to, metadata).await?;
+/// store.commit_transaction(tx).await?;
+/// ```
+#[async_trait]
+pub trait Transactional<A>: EventStore<A>
+where
+    A: Aggregate,
+{
+    /// The pending changes of a transaction.
+    type Transaction: Send;
+    /// Begins a new transaction.
+    async fn begin(&self) -> Self::Transaction;
+    /// Adds the events to the transaction, returning the events as they will be committed.
+    async fn commit_in_transaction(
+        &self,
+        tx: &mut Self::Transaction,
+        events: Vec<A::Event>,
+        context: Self::AC,
+        metadata: HashMap<String, String>,
+    ) -> Result<Vec<EventEnvelope<A>>, AggregateError>;
+    /// Atomically commits all events added to the transaction.
+    async fn commit_transaction(&self, tx: Self::Transaction) -> Result<(), AggregateError>;
+    /// Discards all events added to the transaction.
+    async fn rollback_transaction(&self, tx: Self::Transaction);
+}
+
+/// A liveness check for the backing event store, useful for implementing liveness and
+/// readiness probes in production deployments.
+#[async_trait]
+pub trait EventStoreHealthCheck: Send + Sync {
+    /// Verifies that the backing store is reachable and able to serve requests.
+    async fn ping(&self) -> Result<(), HealthCheckError>;
+}
+
+/// Allows event store wrappers that add cross-cutting behavior (e.g., logging, metrics, retries)
+/// to be composed as a readable chain rather than as nested constructor calls.
+///
+/// This is implemented for every `EventStore`, each call to `decorate` consumes the store and
+/// passes it to the provided decorator, returning the wrapped store.
+///
+/// ```ignore
+/// let store = PgEventStore::new(pool)
+///     .decorate(TracingEventStore::new)
+///     .decorate(|store| CircuitBreakerEventStore::new(store, 5))
+///     .decorate(InstrumentedEventStore::new);
+/// let cqrs = CqrsFramework::new(store, vec![]);
+/// ```
+pub trait EventStoreDecorator<A>: EventStore<A> + Sized
+where
+    A: Aggregate,
+{
+    /// Wraps this store using the provided decorator.
+    fn decorate<D, F>(self, decorator: F) -> D
+    where
+        D: EventStore<A>,
+        F: FnOnce(Self) -> D,
+    {
+        decorator(self)
+    }
+}
+
+impl<A, ES> EventStoreDecorator<A> for ES
+where
+    A: Aggregate,
+    ES: EventStore<A>,
+{
+}
+
 /// Returns the aggregate and context around it that is needed when committing events
 pub trait AggregateContext<A>
 where
@@ -63,4 +292,23 @@ where
 {
     /// The aggregate instance with all state loaded.
     fn aggregate(&self) -> &A;
+    /// The ID of the aggregate instance held by this context, allowing event store wrappers to
+    /// identify the aggregate instance being committed.
+    ///
+    /// Event stores should override this, the default returns `None`.
+    fn aggregate_id(&self) -> Option<&str> {
+        None
+    }
+    /// The sequence number of the last event applied to the aggregate instance held by this
+    /// context, `0` for an aggregate instance with no events.
+    ///
+    /// Event stores should override this, the default returns `None`.
+    fn current_sequence(&self) -> Option<usize> {
+        None
+    }
+    /// Provides the aggregate ID held by this context to an aggregate instance, see
+    /// [`Aggregate::set_aggregate_id`](trait.Aggregate.html#method.set_aggregate_id).
+    ///
+    /// Event stores should call this when loading an aggregate, the default does nothing.
+    fn set_id_on_aggregate(&self, _aggregate: &mut A) {}
 }
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+
+use crate::event::EventEnvelope;
+use crate::{Aggregate, AggregateError, CountableEventStore, EventEnvelopeStream, EventStore};
+
+/// The metadata key holding the ID of the tenant that committed an event.
+pub const TENANT_ID_METADATA_KEY: &str = "tenant_id";
+
+/// Identifies the tenant on whose behalf an event store is accessed.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use cqrs_es::{TenantContext, TENANT_ID_METADATA_KEY};
+///
+/// let mut metadata = HashMap::new();
+/// metadata.insert(TENANT_ID_METADATA_KEY.to_string(), "acme".to_string());
+/// let tenant = TenantContext::from_metadata(&metadata).unwrap();
+/// assert_eq!("acme", tenant.tenant_id());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TenantContext {
+    tenant_id: String,
+}
+
+impl TenantContext {
+    /// Creates a context for the provided tenant.
+    pub fn new(tenant_id: impl Into<String>) -> Self {
+        TenantContext {
+            tenant_id: tenant_id.into(),
+        }
+    }
+    /// Extracts the tenant from metadata under the
+    /// [`TENANT_ID_METADATA_KEY`](constant.TENANT_ID_METADATA_KEY.html), if present.
+    pub fn from_metadata(metadata: &HashMap<String, String>) -> Option<Self> {
+        metadata.get(TENANT_ID_METADATA_KEY).map(TenantContext::new)
+    }
+    /// The ID of the tenant.
+    pub fn tenant_id(&self) -> &str {
+        &self.tenant_id
+    }
+}
+
+/// An event store wrapper that isolates the events of a single tenant by namespacing every
+/// aggregate ID as `"{tenant_id}/{aggregate_id}"` within the wrapped store. Any `%` or `/` within
+/// the tenant ID is percent-encoded in the namespace, so that the namespaces of two tenants never
+/// overlap.
+///
+/// The prefix is removed from the aggregate ID of all returned events, so the tenant is never
+/// aware of the namespacing. The tenant ID is also recorded in the metadata of each committed
+/// event under the [`TENANT_ID_METADATA_KEY`]. Note that `count_events` is not scoped to the
+/// tenant and counts the events of all tenants in the wrapped store, and that an aggregate
+/// overriding `set_aggregate_id` is provided with the namespaced ID.
+///
+/// ```
+/// # use cqrs_es::doc::MyAggregate;
+/// use std::sync::Arc;
+/// use cqrs_es::{CqrsFramework, TenantAwareEventStore, TenantContext};
+/// use cqrs_es::mem_store::MemStore;
+///
+/// let store = Arc::new(MemStore::<MyAggregate>::default());
+/// let acme_store = TenantAwareEventStore::new(store.clone(), TenantContext::new("acme"));
+/// let acme_cqrs = CqrsFramework::new(acme_store, vec![]);
+/// ```
+pub struct TenantAwareEventStore<ES> {
+    store: ES,
+    tenant: TenantContext,
+}
+
+impl<ES> TenantAwareEventStore<ES> {
+    /// Wraps the provided event store, restricting all access to the events of the tenant.
+    pub fn new(store: ES, tenant: TenantContext) -> Self {
+        TenantAwareEventStore { store, tenant }
+    }
+    fn tenant_prefix(&self) -> String {
+        let tenant_id = self
+            .tenant
+            .tenant_id
+            .replace('%', "%25")
+            .replace('/', "%2F");
+        format!("{}/", tenant_id)
+    }
+    fn tenant_aggregate_id(&self, aggregate_id: &str) -> String {
+        format!("{}{}", self.tenant_prefix(), aggregate_id)
+    }
+    // Events outside of the namespace of the tenant are never returned.
+    fn strip_tenant<A: Aggregate>(&self, events: Vec<EventEnvelope<A>>) -> Vec<EventEnvelope<A>> {
+        let prefix = self.tenant_prefix();
+        events
+            .into_iter()
+            .filter_map(|mut event| {
+                event.aggregate_id = event.aggregate_id.strip_prefix(&prefix)?.to_string();
+                Some(event)
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl<A, ES> EventStore<A> for TenantAwareEventStore<ES>
+where
+    A: Aggregate + 'static,
+    ES: EventStore<A> + 'static,
+{
+    type AC = ES::AC;
+
+    async fn load(&self, aggregate_id: &str) -> Vec<EventEnvelope<A>> {
+        let aggregate_id = self.tenant_aggregate_id(aggregate_id);
+        self.strip_tenant(self.store.load(&aggregate_id).await)
+    }
+
+    fn load_stream<'a>(&'a self, aggregate_id: &'a str) -> EventEnvelopeStream<'a, A>
+    where
+        A: 'a,
+    {
+        Box::pin(stream::once(self.load(aggregate_id)).flat_map(stream::iter))
+    }
+
+    async fn load_aggregate(&self, aggregate_id: &str) -> Self::AC {
+        let aggregate_id = self.tenant_aggregate_id(aggregate_id);
+        self.store.load_aggregate(&aggregate_id).await
+    }
+
+    async fn last_event_version(&self, aggregate_id: &str) -> Option<usize> {
+        let aggregate_id = self.tenant_aggregate_id(aggregate_id);
+        self.store.last_event_version(&aggregate_id).await
+    }
+
+    async fn is_duplicate_command(&self, aggregate_id: &str, command_id: &str) -> bool {
+        let aggregate_id = self.tenant_aggregate_id(aggregate_id);
+        self.store
+            .is_duplicate_command(&aggregate_id, command_id)
+            .await
+    }
+
+    async fn commit(
+        &self,
+        events: Vec<A::Event>,
+        context: Self::AC,
+        mut metadata: HashMap<String, String>,
+    ) -> Result<Vec<EventEnvelope<A>>, AggregateError> {
+        metadata.insert(
+            TENANT_ID_METADATA_KEY.to_string(),
+            self.tenant.tenant_id.clone(),
+        );
+        let committed_events = self.store.commit(events, context, metadata).await?;
+        Ok(self.strip_tenant(committed_events))
+    }
+}
+
+#[async_trait]
+impl<A, ES> CountableEventStore<A> for TenantAwareEventStore<ES>
+where
+    A: Aggregate + 'static,
+    ES: CountableEventStore<A> + 'static,
+{
+    async fn count_events(&self) -> usize {
+        self.store.count_events().await
+    }
+}
@@ -1,6 +1,12 @@
 use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
 
 use crate::aggregate::Aggregate;
+use crate::event::{DomainEvent, EventEnvelope};
+use crate::query::Query;
+use crate::snapshot::{restore_from_snapshot, AggregateSnapshot};
 use crate::AggregateError;
 
 /// A framework for rigorously testing the aggregate logic, one of the ***most important***
@@ -31,7 +37,10 @@ where
     /// ```
     #[must_use]
     pub fn given_no_previous_events(&self) -> AggregateTestExecutor<A> {
-        AggregateTestExecutor { events: Vec::new() }
+        AggregateTestExecutor {
+            aggregate: A::default(),
+            events: Vec::new(),
+        }
     }
     /// Initiates an aggregate test with a collection of previous events.
     ///
@@ -44,7 +53,49 @@ where
     /// ```
     #[must_use]
     pub fn given(&self, events: Vec<A::Event>) -> AggregateTestExecutor<A> {
-        AggregateTestExecutor { events }
+        AggregateTestExecutor {
+            aggregate: A::default(),
+            events,
+        }
+    }
+    /// Initiates an aggregate test with the aggregate restored from a snapshot rather than
+    /// replayed from events. Any events given afterward with `then_given` are applied to the
+    /// restored aggregate.
+    ///
+    /// ```
+    /// use cqrs_es::doc::Customer;
+    /// use cqrs_es::take_snapshot;
+    /// use cqrs_es::test::TestFramework;
+    ///
+    /// let snapshot = take_snapshot(&Customer::default(), 3);
+    /// let executor = TestFramework::<Customer>::default()
+    ///     .given_snapshot(snapshot);
+    /// ```
+    #[must_use]
+    pub fn given_snapshot(&self, snapshot: AggregateSnapshot<A>) -> AggregateTestExecutor<A> {
+        let (aggregate, _) = restore_from_snapshot(snapshot);
+        AggregateTestExecutor {
+            aggregate,
+            events: Vec::new(),
+        }
+    }
+    /// Applies the events to a default aggregate and passes the resulting aggregate to the
+    /// assertion, for tests of the aggregate state that do not involve a command.
+    ///
+    /// ```
+    /// use cqrs_es::doc::{Customer, CustomerEvent};
+    /// use cqrs_es::test::TestFramework;
+    ///
+    /// TestFramework::<Customer>::assert_aggregate_state_after_events(
+    ///     vec![CustomerEvent::NameAdded { changed_name: "John Doe".to_string() }],
+    ///     |customer| assert_eq!("John Doe", customer.name),
+    /// );
+    /// ```
+    pub fn assert_aggregate_state_after_events<F>(events: Vec<A::Event>, assertion: F)
+    where
+        F: Fn(&A),
+    {
+        assertion(&A::hydrate_from_events(events));
     }
 }
 
@@ -64,6 +115,7 @@ pub struct AggregateTestExecutor<A>
 where
     A: Aggregate,
 {
+    aggregate: A,
     events: Vec<A::Event>,
 }
 
@@ -71,6 +123,22 @@ impl<A> AggregateTestExecutor<A>
 where
     A: Aggregate,
 {
+    /// Appends additional events to those previously given, allowing the initial state of the
+    /// aggregate to be built up in stages.
+    ///
+    /// ```
+    /// # use cqrs_es::doc::{MyAggregate, MyEvents};
+    /// use cqrs_es::test::TestFramework;
+    ///
+    /// let executor = TestFramework::<MyAggregate>::default()
+    ///     .given(vec![MyEvents::SomethingWasDone])
+    ///     .then_given(vec![MyEvents::SomethingWasDone]);
+    /// ```
+    #[must_use]
+    pub fn then_given(mut self, additional_events: Vec<A::Event>) -> Self {
+        self.events.extend(additional_events);
+        self
+    }
     /// Consumes a command and using the state details previously passed provides a validator object
     /// to test against.
     ///
@@ -82,13 +150,83 @@ where
     ///
     /// let validator = executor.when(MyCommands::DoSomething);
     /// ```
+    #[must_use = "the AggregateResultValidator must be consumed with then_expect_events, then_expect_state or then_expect_error"]
     pub fn when(self, command: A::Command) -> AggregateResultValidator<A> {
-        let mut aggregate = A::default();
+        let mut aggregate = self.aggregate;
+        for event in self.events {
+            aggregate.apply(event);
+        }
+        let result = aggregate.handle(command);
+        AggregateResultValidator { aggregate, result }
+    }
+    /// Consumes a command, returning the aggregate with any resulting events applied along with
+    /// the result of the command. This allows the state after each command to be inspected and
+    /// used to compose further commands within a single test.
+    ///
+    /// ```
+    /// use cqrs_es::doc::{Customer, CustomerCommand};
+    /// use cqrs_es::test::TestFramework;
+    ///
+    /// let executor = TestFramework::<Customer>::default().given_no_previous_events();
+    ///
+    /// let (customer, result) = executor.when_and_apply(CustomerCommand::AddCustomerName {
+    ///     changed_name: "John Doe".to_string(),
+    /// });
+    /// assert!(result.is_ok());
+    /// assert_eq!("John Doe", customer.name);
+    /// ```
+    pub fn when_and_apply(self, command: A::Command) -> (A, Result<Vec<A::Event>, AggregateError>) {
+        let mut aggregate = self.aggregate;
         for event in self.events {
             aggregate.apply(event);
         }
         let result = aggregate.handle(command);
-        AggregateResultValidator { result }
+        if let Ok(events) = &result {
+            for event in events {
+                aggregate.apply(event.clone());
+            }
+        }
+        (aggregate, result)
+    }
+    /// Consumes a sequence of commands, applying the events produced by each command to the
+    /// aggregate before handling the next. Every command other than the last must succeed,
+    /// the validator tests against the result of the final command.
+    ///
+    /// ```
+    /// # use cqrs_es::doc::{MyAggregate, MyCommands};
+    /// use cqrs_es::test::TestFramework;
+    ///
+    /// let executor = TestFramework::<MyAggregate>::default().given_no_previous_events();
+    ///
+    /// let validator = executor.when_multiple(vec![MyCommands::DoSomething, MyCommands::DoSomething]);
+    /// ```
+    #[must_use = "the AggregateResultValidator must be consumed with then_expect_events, then_expect_state or then_expect_error"]
+    pub fn when_multiple(self, mut commands: Vec<A::Command>) -> AggregateResultValidator<A> {
+        let final_command = match commands.pop() {
+            Some(command) => command,
+            None => panic!("expected at least one command"),
+        };
+        let mut aggregate = self.aggregate;
+        for event in self.events {
+            aggregate.apply(event);
+        }
+        for (position, command) in commands.into_iter().enumerate() {
+            match aggregate.handle(command) {
+                Ok(events) => {
+                    for event in events {
+                        aggregate.apply(event);
+                    }
+                }
+                Err(err) => {
+                    panic!(
+                        "expected success for command {}, received aggregate error: '{}'",
+                        position, err
+                    );
+                }
+            }
+        }
+        let result = aggregate.handle(final_command);
+        AggregateResultValidator { aggregate, result }
     }
 }
 
@@ -97,6 +235,7 @@ pub struct AggregateResultValidator<A>
 where
     A: Aggregate,
 {
+    aggregate: A,
     result: Result<Vec<A::Event>, AggregateError>,
 }
 
@@ -122,6 +261,76 @@ impl<A: Aggregate> AggregateResultValidator<A> {
         };
         assert_eq!(&events[..], &expected_events[..]);
     }
+    /// Verifies that the expected events have been produced by the command, in any order. This
+    /// is useful for commands that produce events in a non-deterministic order, e.g., while
+    /// iterating over a `HashMap`.
+    ///
+    /// ```
+    /// # use cqrs_es::doc::{MyAggregate, MyCommands, MyEvents};
+    /// use cqrs_es::test::TestFramework;
+    ///
+    /// let validator = TestFramework::<MyAggregate>::default()
+    ///     .given_no_previous_events()
+    ///     .when(MyCommands::DoSomething);
+    ///
+    /// validator.then_expect_events_in_any_order(vec![MyEvents::SomethingWasDone]);
+    /// ```
+    pub fn then_expect_events_in_any_order(self, mut expected_events: Vec<A::Event>) {
+        let mut events = match self.result {
+            Ok(events) => events,
+            Err(err) => {
+                panic!("expected success, received aggregate error: '{}'", err);
+            }
+        };
+        events.sort_by(|a, b| a.event_type().cmp(b.event_type()));
+        expected_events.sort_by(|a, b| a.event_type().cmp(b.event_type()));
+        let mut unmatched: Vec<&A::Event> = events.iter().collect();
+        let all_matched = expected_events.iter().all(|expected| {
+            match unmatched.iter().position(|event| *event == expected) {
+                Some(position) => {
+                    unmatched.remove(position);
+                    true
+                }
+                None => false,
+            }
+        });
+        if !all_matched || !unmatched.is_empty() {
+            panic!(
+                "expected events in any order: {:?}\n received events: {:?}",
+                expected_events, events
+            );
+        }
+    }
+    /// Verifies the state of the aggregate after the events produced by the command have been
+    /// applied, panicking with the provided description if the predicate returns `false`.
+    ///
+    /// ```
+    /// use cqrs_es::doc::{Customer, CustomerCommand};
+    /// use cqrs_es::test::TestFramework;
+    ///
+    /// let validator = TestFramework::<Customer>::default()
+    ///     .given_no_previous_events()
+    ///     .when(CustomerCommand::AddCustomerName {
+    ///         changed_name: "John Doe".to_string(),
+    ///     });
+    ///
+    /// validator.then_expect_state(|customer| customer.name == "John Doe", "customer is named");
+    /// ```
+    pub fn then_expect_state<F: Fn(&A) -> bool>(self, predicate: F, description: &str) {
+        let events = match self.result {
+            Ok(events) => events,
+            Err(err) => {
+                panic!("expected success, received aggregate error: '{}'", err);
+            }
+        };
+        let mut aggregate = self.aggregate;
+        for event in events {
+            aggregate.apply(event);
+        }
+        if !predicate(&aggregate) {
+            panic!("unexpected aggregate state: {}", description);
+        }
+    }
     /// Verifies that an `AggregateError` with the expected message is produced with the command.
     ///
     /// ```
@@ -149,6 +358,93 @@ impl<A: Aggregate> AggregateResultValidator<A> {
             },
         };
     }
+    /// Verifies that an `AggregateError` is produced with the command and that its message
+    /// contains the expected substring. This is less brittle than `then_expect_error` when the
+    /// wording of error messages changes over time.
+    ///
+    /// ```
+    /// # use cqrs_es::doc::{MyAggregate, MyCommands, MyEvents};
+    /// use cqrs_es::test::TestFramework;
+    ///
+    /// let validator = TestFramework::<MyAggregate>::default()
+    ///     .given_no_previous_events()
+    ///     .when(MyCommands::BadCommand);
+    ///
+    /// validator.then_expect_error_containing("expected error");
+    /// ```
+    pub fn then_expect_error_containing(self, substring: &str) {
+        let message = match self.result {
+            Ok(events) => {
+                panic!("expected error, received events: '{:?}'", events);
+            }
+            Err(AggregateError::UserError(payload)) => payload.message.unwrap_or_default(),
+            Err(err) => err.to_string(),
+        };
+        if !message.contains(substring) {
+            panic!(
+                "expected error containing '{}', received error: '{}'",
+                substring, message
+            );
+        }
+    }
+}
+
+/// A `Query` that collects all dispatched events for assertions in integration tests. Clones
+/// share the same collected events, so a clone may be registered with a `CqrsFramework` while
+/// the original is retained for assertions.
+///
+/// ```
+/// # use cqrs_es::doc::Customer;
+/// use std::sync::Arc;
+/// use cqrs_es::CqrsFramework;
+/// use cqrs_es::mem_store::MemStore;
+/// use cqrs_es::test::TestEventPublisher;
+///
+/// let publisher = TestEventPublisher::<Customer>::default();
+/// let store = MemStore::<Customer>::default();
+/// let cqrs = CqrsFramework::new(store, vec![Arc::new(publisher.clone())]);
+/// //...
+/// assert_eq!(0, publisher.event_count());
+/// ```
+pub struct TestEventPublisher<A: Aggregate> {
+    events: Arc<Mutex<Vec<EventEnvelope<A>>>>,
+}
+
+impl<A: Aggregate> Default for TestEventPublisher<A> {
+    fn default() -> Self {
+        TestEventPublisher {
+            events: Default::default(),
+        }
+    }
+}
+
+impl<A: Aggregate> Clone for TestEventPublisher<A> {
+    fn clone(&self) -> Self {
+        TestEventPublisher {
+            events: Arc::clone(&self.events),
+        }
+    }
+}
+
+impl<A: Aggregate> TestEventPublisher<A> {
+    /// A copy of all events dispatched so far, in the order they were dispatched.
+    pub fn events(&self) -> Vec<EventEnvelope<A>> {
+        // uninteresting unwrap: this is not a struct for production use
+        self.events.lock().unwrap().clone()
+    }
+    /// The number of events dispatched so far.
+    pub fn event_count(&self) -> usize {
+        // uninteresting unwrap: this is not a struct for production use
+        self.events.lock().unwrap().len()
+    }
+}
+
+#[async_trait]
+impl<A: Aggregate> Query<A> for TestEventPublisher<A> {
+    async fn dispatch(&self, _aggregate_id: &str, events: &[EventEnvelope<A>]) {
+        // uninteresting unwrap: this is not a struct for production use
+        self.events.lock().unwrap().extend_from_slice(events);
+    }
 }
 
 #[cfg(test)]
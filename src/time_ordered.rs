@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use chrono::DateTime;
+use tokio::sync::Mutex;
+
+use crate::event::EventEnvelope;
+use crate::{Aggregate, AggregateError, CountableEventStore, EventEnvelopeStream, EventStore};
+
+/// The metadata key holding the RFC-3339 timestamp of a commit.
+pub const TIME_METADATA_KEY: &str = "time";
+
+/// An event store wrapper that enforces strictly increasing timestamps across all commits,
+/// as is required for some audit purposes.
+///
+/// Each commit must provide an RFC-3339 timestamp in the metadata under the
+/// [`TIME_METADATA_KEY`], a commit with a missing or malformed timestamp, or with a timestamp that
+/// is not later than that of the previous commit, is rejected with an
+/// `AggregateError::TechnicalError`. Commits are serialized to guarantee the ordering.
+///
+/// ```
+/// # use cqrs_es::doc::MyAggregate;
+/// use cqrs_es::{CqrsFramework, TimeOrderedEventStore};
+/// use cqrs_es::mem_store::MemStore;
+///
+/// let store = TimeOrderedEventStore::new(MemStore::<MyAggregate>::default());
+/// let cqrs = CqrsFramework::new(store, vec![]);
+/// ```
+pub struct TimeOrderedEventStore<ES> {
+    store: ES,
+    last_commit_time: Mutex<SystemTime>,
+}
+
+impl<ES> TimeOrderedEventStore<ES> {
+    /// Wraps the provided event store, enforcing the order of commit timestamps.
+    pub fn new(store: ES) -> Self {
+        TimeOrderedEventStore {
+            store,
+            last_commit_time: Mutex::new(UNIX_EPOCH),
+        }
+    }
+}
+
+fn commit_time(metadata: &HashMap<String, String>) -> Result<SystemTime, AggregateError> {
+    let time = match metadata.get(TIME_METADATA_KEY) {
+        Some(time) => time,
+        None => {
+            return Err(AggregateError::TechnicalError(format!(
+                "commit is missing the '{}' metadata",
+                TIME_METADATA_KEY
+            )))
+        }
+    };
+    match DateTime::parse_from_rfc3339(time) {
+        Ok(time) => Ok(time.into()),
+        Err(err) => Err(AggregateError::TechnicalError(format!(
+            "invalid commit time '{}': {}",
+            time, err
+        ))),
+    }
+}
+
+#[async_trait]
+impl<A, ES> EventStore<A> for TimeOrderedEventStore<ES>
+where
+    A: Aggregate + 'static,
+    ES: EventStore<A> + 'static,
+{
+    type AC = ES::AC;
+
+    async fn load(&self, aggregate_id: &str) -> Vec<EventEnvelope<A>> {
+        self.store.load(aggregate_id).await
+    }
+
+    fn load_stream<'a>(&'a self, aggregate_id: &'a str) -> EventEnvelopeStream<'a, A>
+    where
+        A: 'a,
+    {
+        self.store.load_stream(aggregate_id)
+    }
+
+    async fn load_aggregate(&self, aggregate_id: &str) -> Self::AC {
+        self.store.load_aggregate(aggregate_id).await
+    }
+
+    async fn last_event_version(&self, aggregate_id: &str) -> Option<usize> {
+        self.store.last_event_version(aggregate_id).await
+    }
+
+    async fn is_duplicate_command(&self, aggregate_id: &str, command_id: &str) -> bool {
+        self.store
+            .is_duplicate_command(aggregate_id, command_id)
+            .await
+    }
+
+    async fn commit(
+        &self,
+        events: Vec<A::Event>,
+        context: Self::AC,
+        metadata: HashMap<String, String>,
+    ) -> Result<Vec<EventEnvelope<A>>, AggregateError> {
+        if events.is_empty() {
+            return self.store.commit(events, context, metadata).await;
+        }
+        let commit_time = commit_time(&metadata)?;
+        let mut last_commit_time = self.last_commit_time.lock().await;
+        if commit_time <= *last_commit_time {
+            return Err(AggregateError::TechnicalError(format!(
+                "commit time '{}' is not later than the previous commit",
+                metadata[TIME_METADATA_KEY]
+            )));
+        }
+        let committed_events = self.store.commit(events, context, metadata).await?;
+        *last_commit_time = commit_time;
+        Ok(committed_events)
+    }
+}
+
+#[async_trait]
+impl<A, ES> CountableEventStore<A> for TimeOrderedEventStore<ES>
+where
+    A: Aggregate + 'static,
+    ES: CountableEventStore<A> + 'static,
+{
+    async fn count_events(&self) -> usize {
+        self.store.count_events().await
+    }
+}
@@ -1,13 +1,30 @@
 use async_trait::async_trait;
-use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
 
-use cqrs_es::mem_store::MemStore;
-use cqrs_es::test::TestFramework;
-use cqrs_es::Query;
-use cqrs_es::{Aggregate, AggregateError, CqrsFramework, DomainEvent, EventEnvelope, EventStore};
+use cqrs_es::doc::{Customer, CustomerCommand, CustomerEvent};
+use cqrs_es::mem_store::{MemStore, MemStoreAggregateContext};
+use cqrs_es::test::{TestEventPublisher, TestFramework};
+use cqrs_es::{
+    downcast_events, restore_from_snapshot, take_snapshot, Aggregate, AggregateContext,
+    AggregateError, BulkEventStore, CheckpointStore, Checkpointed, CommandAuditLog, CommandContext,
+    CommandDispatchError, CommandEnvelope, CommandHandler, CommandRateLimiter, CommandValidator,
+    CompactableAggregate, CompactableEventStore, CompactingEventStore, CompensatingCommandRunner,
+    Compensator, CountableEventStore, CqrsFramework, DeduplicatingEventStore, DomainEvent,
+    DoubleDispatch, ErasedQuery, EventEnvelope, EventFilter, EventIdentity, EventMigrator,
+    EventSerializer, EventStore, EventStoreDecorator, EventStoreHealthCheck, FilteredQuery,
+    JsonEventSerializer, LoggingEventStore, MemCheckpointStore, MemOutboxStore, MemReadModelStore,
+    MirroredEventStore, MultiAggregateQuery, OutboxEventStore, PaginatedEventStore, QueryError,
+    RawEventEnvelope, ReadModelStore, SnapshotEnvelope, TenantAwareEventStore, TenantContext,
+    TimeOrderedEventStore, Transactional, UserErrorPayload, ValidationError, VersionedAggregate,
+    COMMAND_ID_METADATA_KEY, TIME_METADATA_KEY,
+};
+use cqrs_es::{Query, WeakConsistencyQuery};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TestAggregate {
@@ -67,8 +84,14 @@ impl Aggregate for TestAggregate {
             }
         }
     }
+    fn on_create(&self) {
+        CREATED_AGGREGATES.lock().unwrap().push(self.id.clone());
+    }
 }
 
+// The IDs of the aggregates created within any test, recorded by `on_create`.
+static CREATED_AGGREGATES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
 impl Default for TestAggregate {
     fn default() -> Self {
         TestAggregate {
@@ -113,6 +136,18 @@ impl DomainEvent for TestEvent {
     fn event_version(&self) -> &'static str {
         "1.0"
     }
+
+    fn aggregate_changes(&self) -> Vec<&'static str> {
+        match self {
+            TestEvent::Created(_) => vec!["id"],
+            TestEvent::Tested(_) => vec!["tests"],
+            TestEvent::SomethingElse(_) => vec!["description"],
+        }
+    }
+
+    fn is_internal(&self) -> bool {
+        matches!(self, TestEvent::SomethingElse(_))
+    }
 }
 
 pub enum TestCommand {
@@ -165,9 +200,9 @@ fn metadata() -> HashMap<String, String> {
 async fn test_mem_store() {
     let event_store = MemStore::<TestAggregate>::default();
     let id = "test_id_A";
-    let initial_events = event_store.load(&id).await;
+    let initial_events = event_store.load(id).await;
     assert_eq!(0, initial_events.len());
-    let agg_context = event_store.load_aggregate(&id).await;
+    let agg_context = event_store.load_aggregate(id).await;
 
     event_store
         .commit(
@@ -179,9 +214,9 @@ async fn test_mem_store() {
         )
         .await
         .unwrap();
-    let stored_events = event_store.load(&id).await;
+    let stored_events = event_store.load(id).await;
     assert_eq!(1, stored_events.len());
-    let agg_context = event_store.load_aggregate(&id).await;
+    let agg_context = event_store.load_aggregate(id).await;
 
     event_store
         .commit(
@@ -201,7 +236,7 @@ async fn test_mem_store() {
         )
         .await
         .unwrap();
-    let stored_envelopes = event_store.load(&id).await;
+    let stored_envelopes = event_store.load(id).await;
 
     let mut agg = TestAggregate::default();
     for stored_envelope in stored_envelopes {
@@ -211,6 +246,33 @@ async fn test_mem_store() {
     println!("{:#?}", agg);
 }
 
+#[test]
+fn aggregate_hydrate_from_events_test() {
+    let agg = TestAggregate::hydrate_from_events(vec![
+        TestEvent::Created(Created {
+            id: "test_id_A".to_string(),
+        }),
+        TestEvent::Tested(Tested {
+            test_name: "test A".to_string(),
+        }),
+        TestEvent::Tested(Tested {
+            test_name: "test B".to_string(),
+        }),
+        TestEvent::SomethingElse(SomethingElse {
+            description: "something else happening here".to_string(),
+        }),
+    ]);
+    assert_eq!("test_id_A", agg.id);
+    assert_eq!(vec!["test A", "test B"], agg.tests);
+    assert_eq!("something else happening here", agg.description);
+
+    let empty = TestAggregate::hydrate_from_events(vec![]);
+    let default = TestAggregate::default();
+    assert_eq!(default.id, empty.id);
+    assert_eq!(default.description, empty.description);
+    assert_eq!(default.tests, empty.tests);
+}
+
 type ThisTestFramework = TestFramework<TestAggregate>;
 
 #[test]
@@ -239,6 +301,67 @@ fn test_framework_test() {
         .then_expect_error("test already performed")
 }
 
+#[test]
+fn test_framework_then_given_test() {
+    let test_name = "test A";
+    let test_framework = ThisTestFramework::default();
+
+    test_framework
+        .given(vec![TestEvent::Created(Created {
+            id: "test_id_A".to_string(),
+        })])
+        .then_given(vec![TestEvent::Tested(Tested {
+            test_name: test_name.to_string(),
+        })])
+        .when(TestCommand::ConfirmTest(ConfirmTest {
+            test_name: test_name.to_string(),
+        }))
+        .then_expect_error("test already performed")
+}
+
+#[test]
+fn test_framework_when_multiple_test() {
+    let test_framework = ThisTestFramework::default();
+
+    test_framework
+        .given_no_previous_events()
+        .when_multiple(vec![
+            TestCommand::CreateTest(CreateTest {
+                id: "test_id_A".to_string(),
+            }),
+            TestCommand::ConfirmTest(ConfirmTest {
+                test_name: "test A".to_string(),
+            }),
+            TestCommand::ConfirmTest(ConfirmTest {
+                test_name: "test A".to_string(),
+            }),
+        ])
+        .then_expect_error("test already performed")
+}
+
+#[test]
+#[should_panic]
+fn test_framework_when_multiple_failure_test() {
+    let test_framework = ThisTestFramework::default();
+
+    test_framework
+        .given_no_previous_events()
+        .when_multiple(vec![
+            TestCommand::ConfirmTest(ConfirmTest {
+                test_name: "test A".to_string(),
+            }),
+            TestCommand::ConfirmTest(ConfirmTest {
+                test_name: "test A".to_string(),
+            }),
+            TestCommand::DoSomethingElse(DoSomethingElse {
+                description: "something else".to_string(),
+            }),
+        ])
+        .then_expect_events(vec![TestEvent::SomethingElse(SomethingElse {
+            description: "something else".to_string(),
+        })]);
+}
+
 #[test]
 #[should_panic]
 fn test_framework_failure_test() {
@@ -339,3 +462,3102 @@ async fn framework_test() {
         .len();
     assert_eq!(2, stored_event_count);
 }
+
+#[tokio::test]
+async fn framework_observer_test() {
+    let observed_events: Arc<RwLock<Vec<String>>> = Default::default();
+    let first_observer = Arc::clone(&observed_events);
+    let second_observer = Arc::clone(&observed_events);
+    let cqrs = CqrsFramework::new(MemStore::<TestAggregate>::default(), vec![])
+        .with_observer(move |events| {
+            for event in events {
+                let mut observed = first_observer.write().unwrap();
+                observed.push(format!("first-{}", event.sequence));
+            }
+        })
+        .with_observer(move |events| {
+            for event in events {
+                let mut observed = second_observer.write().unwrap();
+                observed.push(format!("second-{}", event.sequence));
+            }
+        });
+
+    cqrs.execute(
+        "test_id_A",
+        TestCommand::CreateTest(CreateTest {
+            id: "test_id_A".to_string(),
+        }),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        vec!["first-1".to_string(), "second-1".to_string()],
+        *observed_events.read().unwrap()
+    );
+}
+
+#[tokio::test]
+async fn mem_store_aggregate_ids_test() {
+    let event_store = MemStore::<TestAggregate>::default();
+    assert!(event_store.aggregate_ids().is_empty());
+    for id in ["test_id_A", "test_id_B"] {
+        let agg_context = event_store.load_aggregate(id).await;
+        event_store
+            .commit(
+                vec![TestEvent::Created(Created { id: id.to_string() })],
+                agg_context,
+                metadata(),
+            )
+            .await
+            .unwrap();
+    }
+
+    let mut aggregate_ids = event_store.aggregate_ids();
+    aggregate_ids.sort();
+    assert_eq!(vec!["test_id_A", "test_id_B"], aggregate_ids);
+}
+
+#[tokio::test]
+async fn mem_store_clear_aggregate_test() {
+    let event_store = MemStore::<TestAggregate>::default();
+    let mut metadata = metadata();
+    metadata.insert(COMMAND_ID_METADATA_KEY.to_string(), "command_A".to_string());
+    for id in ["test_id_A", "test_id_B"] {
+        let agg_context = event_store.load_aggregate(id).await;
+        event_store
+            .commit(
+                vec![TestEvent::Created(Created { id: id.to_string() })],
+                agg_context,
+                metadata.clone(),
+            )
+            .await
+            .unwrap();
+    }
+
+    assert!(event_store.clear_aggregate("test_id_A"));
+    assert!(!event_store.clear_aggregate("test_id_A"));
+    assert_eq!(0, event_store.load("test_id_A").await.len());
+    assert_eq!(1, event_store.load("test_id_B").await.len());
+
+    // the command that produced the cleared events may be executed again
+    assert!(
+        !event_store
+            .is_duplicate_command("test_id_A", "command_A")
+            .await
+    );
+    assert!(
+        event_store
+            .is_duplicate_command("test_id_B", "command_A")
+            .await
+    );
+}
+
+#[tokio::test]
+async fn framework_execute_if_exists_test() {
+    let cqrs = CqrsFramework::new(MemStore::<TestAggregate>::default(), vec![]);
+    let id = "test_id_A";
+    let err = cqrs
+        .execute_if_exists(
+            id,
+            TestCommand::ConfirmTest(ConfirmTest {
+                test_name: "test A".to_string(),
+            }),
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(AggregateError::NotFound(id.to_string()), err);
+
+    cqrs.execute(
+        id,
+        TestCommand::CreateTest(CreateTest { id: id.to_string() }),
+    )
+    .await
+    .unwrap();
+    cqrs.execute_if_exists(
+        id,
+        TestCommand::ConfirmTest(ConfirmTest {
+            test_name: "test A".to_string(),
+        }),
+    )
+    .await
+    .unwrap();
+}
+
+struct CommitCountingEventStore<ES> {
+    store: ES,
+    commits: Arc<RwLock<usize>>,
+}
+
+impl<ES> CommitCountingEventStore<ES> {
+    fn new(store: ES, commits: Arc<RwLock<usize>>) -> Self {
+        CommitCountingEventStore { store, commits }
+    }
+}
+
+#[async_trait]
+impl<ES: EventStore<TestAggregate>> EventStore<TestAggregate> for CommitCountingEventStore<ES> {
+    type AC = ES::AC;
+
+    async fn load(&self, aggregate_id: &str) -> Vec<TestEventEnvelope> {
+        self.store.load(aggregate_id).await
+    }
+
+    async fn load_aggregate(&self, aggregate_id: &str) -> Self::AC {
+        self.store.load_aggregate(aggregate_id).await
+    }
+
+    async fn commit(
+        &self,
+        events: Vec<TestEvent>,
+        context: Self::AC,
+        metadata: HashMap<String, String>,
+    ) -> Result<Vec<TestEventEnvelope>, AggregateError> {
+        *self.commits.write().unwrap() += 1;
+        self.store.commit(events, context, metadata).await
+    }
+}
+
+#[tokio::test]
+async fn event_store_decorator_test() {
+    let inner_commits: Arc<RwLock<usize>> = Default::default();
+    let outer_commits: Arc<RwLock<usize>> = Default::default();
+    let store = MemStore::<TestAggregate>::default()
+        .decorate(|store| CommitCountingEventStore::new(store, Arc::clone(&inner_commits)))
+        .decorate(|store| CommitCountingEventStore::new(store, Arc::clone(&outer_commits)));
+    let cqrs = CqrsFramework::new(store, vec![]);
+
+    cqrs.execute(
+        "test_id_A",
+        TestCommand::CreateTest(CreateTest {
+            id: "test_id_A".to_string(),
+        }),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(1, *inner_commits.read().unwrap());
+    assert_eq!(1, *outer_commits.read().unwrap());
+}
+
+#[tokio::test]
+async fn weak_consistency_query_test() {
+    let delivered_events = Default::default();
+    let view = Arc::new(WeakConsistencyQuery::new(TestView::new(Arc::clone(
+        &delivered_events,
+    ))));
+    let cqrs = CqrsFramework::new(MemStore::<TestAggregate>::default(), vec![view.clone()]);
+
+    cqrs.execute(
+        "test_id_A",
+        TestCommand::CreateTest(CreateTest {
+            id: "test_id_A".to_string(),
+        }),
+    )
+    .await
+    .unwrap();
+    view.flush().await;
+
+    assert_eq!(1, delivered_events.read().unwrap().len());
+}
+
+#[tokio::test]
+async fn mem_store_health_check_test() {
+    let event_store = MemStore::<TestAggregate>::default();
+    assert_eq!(Ok(()), event_store.ping().await);
+}
+
+#[tokio::test]
+async fn framework_load_and_return_aggregate_test() {
+    let cqrs = CqrsFramework::new(MemStore::<TestAggregate>::default(), vec![]);
+    let id = "test_id_A";
+    let err = cqrs.load_and_return_aggregate(id).await.unwrap_err();
+    assert_eq!(AggregateError::NotFound(id.to_string()), err);
+
+    cqrs.execute(
+        id,
+        TestCommand::CreateTest(CreateTest { id: id.to_string() }),
+    )
+    .await
+    .unwrap();
+    cqrs.execute(
+        id,
+        TestCommand::ConfirmTest(ConfirmTest {
+            test_name: "test A".to_string(),
+        }),
+    )
+    .await
+    .unwrap();
+
+    let aggregate = cqrs.load_and_return_aggregate(id).await.unwrap();
+    assert_eq!(id, aggregate.id);
+    assert_eq!(vec!["test A".to_string()], aggregate.tests);
+}
+
+#[tokio::test]
+async fn outbox_event_store_test() {
+    let outbox = MemOutboxStore::<TestAggregate>::default();
+    let outbox_events = outbox.get_events();
+    let store = OutboxEventStore::new(MemStore::<TestAggregate>::default(), outbox);
+    let cqrs = CqrsFramework::new(store, vec![]);
+    let id = "test_id_A";
+
+    cqrs.execute(
+        id,
+        TestCommand::CreateTest(CreateTest { id: id.to_string() }),
+    )
+    .await
+    .unwrap();
+    cqrs.execute(
+        id,
+        TestCommand::ConfirmTest(ConfirmTest {
+            test_name: "test A".to_string(),
+        }),
+    )
+    .await
+    .unwrap();
+    cqrs.execute(
+        id,
+        TestCommand::DoSomethingElse(DoSomethingElse {
+            description: "internal".to_string(),
+        }),
+    )
+    .await
+    .unwrap();
+
+    let outbox_events = outbox_events.read().unwrap();
+    assert_eq!(2, outbox_events.len());
+    assert_eq!(1, outbox_events[0].sequence);
+    assert_eq!(2, outbox_events[1].sequence);
+}
+
+#[tokio::test]
+async fn framework_command_id_test() {
+    let event_store = MemStore::<TestAggregate>::default();
+    let stored_events = event_store.get_events();
+    let view_events: Arc<RwLock<Vec<EventEnvelope<TestAggregate>>>> = Default::default();
+    let query = TestView::new(view_events.clone());
+    let cqrs = CqrsFramework::new(event_store, vec![Arc::new(query)]);
+    let id = "test_id_A";
+
+    let command = || {
+        CommandEnvelope::new(TestCommand::DoSomethingElse(DoSomethingElse {
+            description: "something else".to_string(),
+        }))
+        .with_command_id("command_A".to_string())
+    };
+    cqrs.execute_envelope(id, command()).await.unwrap();
+    cqrs.execute_envelope(id, command()).await.unwrap();
+    // the same command ID may be used for a different aggregate instance
+    cqrs.execute_envelope("test_id_B", command()).await.unwrap();
+
+    let stored_events = stored_events.read().unwrap();
+    let stored_events_a = stored_events.get(id).unwrap();
+    assert_eq!(1, stored_events_a.len());
+    assert_eq!(
+        Some(&"command_A".to_string()),
+        stored_events_a[0]
+            .metadata
+            .get(cqrs_es::COMMAND_ID_METADATA_KEY)
+    );
+    let stored_events_b = stored_events.get("test_id_B").unwrap();
+    assert_eq!(1, stored_events_b.len());
+
+    // each committed event is dispatched to the query exactly once
+    let view_events = view_events.read().unwrap();
+    assert_eq!(
+        vec![stored_events_a[0].clone(), stored_events_b[0].clone()],
+        *view_events
+    );
+}
+
+#[tokio::test]
+async fn mem_store_seed_events_test() {
+    let mut seed_events = HashMap::new();
+    seed_events.insert(
+        "test_id_A".to_string(),
+        vec![
+            TestEvent::Created(Created {
+                id: "test_id_A".to_string(),
+            }),
+            TestEvent::Tested(Tested {
+                test_name: "test A".to_string(),
+            }),
+        ],
+    );
+    let event_store = MemStore::<TestAggregate>::with_seed_events(seed_events);
+
+    let stored_events = event_store.load("test_id_A").await;
+    assert_eq!(2, stored_events.len());
+    assert_eq!(2, stored_events[1].sequence);
+    assert_eq!("TestAggregate", stored_events[1].aggregate_type);
+
+    let cqrs = CqrsFramework::new(event_store, vec![]);
+    let err = cqrs
+        .execute(
+            "test_id_A",
+            TestCommand::ConfirmTest(ConfirmTest {
+                test_name: "test A".to_string(),
+            }),
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(AggregateError::new("test already performed"), err);
+}
+
+struct PrefixedTestView {
+    prefix: &'static str,
+    view: TestView,
+}
+
+#[async_trait]
+impl Query<TestAggregate> for PrefixedTestView {
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<TestAggregate>]) {
+        self.view.dispatch(aggregate_id, events).await;
+    }
+
+    fn subscribe_to_all(&self) -> bool {
+        false
+    }
+
+    fn subscribes_to(&self, aggregate_id: &str) -> bool {
+        aggregate_id.starts_with(self.prefix)
+    }
+}
+
+#[tokio::test]
+async fn framework_query_subscription_test() {
+    let all_events = Default::default();
+    let prefixed_events = Default::default();
+    let all_view = TestView::new(Arc::clone(&all_events));
+    let prefixed_view = PrefixedTestView {
+        prefix: "special_",
+        view: TestView::new(Arc::clone(&prefixed_events)),
+    };
+    let cqrs = CqrsFramework::new(
+        MemStore::<TestAggregate>::default(),
+        vec![Arc::new(all_view), Arc::new(prefixed_view)],
+    );
+
+    for id in ["test_id_A", "special_id_B"] {
+        cqrs.execute(
+            id,
+            TestCommand::CreateTest(CreateTest { id: id.to_string() }),
+        )
+        .await
+        .unwrap();
+    }
+
+    assert_eq!(2, all_events.read().unwrap().len());
+    let prefixed_events = prefixed_events.read().unwrap();
+    assert_eq!(1, prefixed_events.len());
+    assert_eq!("special_id_B", prefixed_events[0].aggregate_id);
+}
+
+#[tokio::test]
+async fn framework_command_context_test() {
+    let event_store = MemStore::<TestAggregate>::default();
+    let stored_events = event_store.get_events();
+    let cqrs = CqrsFramework::new(event_store, vec![]);
+    let id = "test_id_A";
+
+    let context = CommandContext::default()
+        .with_metadata(metadata())
+        .with_command_id("command_A")
+        .with_idempotency_key("key_A")
+        .with_correlation_id("request_A");
+    cqrs.execute_with_context(
+        id,
+        TestCommand::CreateTest(CreateTest { id: id.to_string() }),
+        context,
+    )
+    .await
+    .unwrap();
+
+    let stored_events = stored_events.read().unwrap();
+    let metadata = &stored_events.get(id).unwrap()[0].metadata;
+    assert_eq!(4, metadata.len());
+    assert_eq!("command_A", metadata[cqrs_es::COMMAND_ID_METADATA_KEY]);
+    assert_eq!("key_A", metadata[cqrs_es::IDEMPOTENCY_KEY_METADATA_KEY]);
+    assert_eq!("request_A", metadata[cqrs_es::CORRELATION_ID_METADATA_KEY]);
+}
+
+#[tokio::test]
+async fn time_ordered_event_store_test() {
+    let store = TimeOrderedEventStore::new(MemStore::<TestAggregate>::default());
+    let cqrs = CqrsFramework::new(store, vec![]);
+    let command = |id: &str| TestCommand::CreateTest(CreateTest { id: id.to_string() });
+    let at = |time: &str| {
+        let mut metadata = HashMap::new();
+        metadata.insert("time".to_string(), time.to_string());
+        metadata
+    };
+
+    cqrs.execute_with_metadata("test_id_A", command("A"), metadata())
+        .await
+        .unwrap();
+    cqrs.execute_with_metadata("test_id_B", command("B"), at("2021-03-18T12:32:46Z"))
+        .await
+        .unwrap();
+
+    for metadata in [
+        at("2021-03-18T12:32:46Z"),
+        at("2021-03-18T12:32:45Z"),
+        at("not a time"),
+        HashMap::new(),
+    ] {
+        let err = cqrs
+            .execute_with_metadata("test_id_C", command("C"), metadata)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AggregateError::TechnicalError(_)));
+    }
+
+    cqrs.execute_with_metadata("test_id_C", command("C"), at("2021-03-18T13:32:47+01:00"))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn aggregate_id_awareness_test() {
+    let event_store = MemStore::<Customer>::default();
+    let agg_context = event_store.load_aggregate("customer_A").await;
+    assert_eq!(Some("customer_A"), agg_context.aggregate().aggregate_id());
+    assert_eq!(None, TestAggregate::default().aggregate_id());
+
+    let cqrs = CqrsFramework::new(event_store, vec![]);
+    cqrs.execute(
+        "customer_A",
+        CustomerCommand::AddCustomerName {
+            changed_name: "John Doe".to_string(),
+        },
+    )
+    .await
+    .unwrap();
+    let customer = cqrs.load_and_return_aggregate("customer_A").await.unwrap();
+    assert_eq!(Some("customer_A"), customer.aggregate_id());
+}
+
+#[tokio::test]
+async fn mem_store_load_page_test() {
+    let mut seed_events = HashMap::new();
+    seed_events.insert(
+        "test_id_A".to_string(),
+        (0..5)
+            .map(|i| {
+                TestEvent::Tested(Tested {
+                    test_name: format!("test {}", i),
+                })
+            })
+            .collect(),
+    );
+    let event_store = MemStore::<TestAggregate>::with_seed_events(seed_events);
+
+    let (events, total) = event_store.load_page("test_id_A", 0, 2).await;
+    assert_eq!(5, total);
+    assert_eq!(
+        vec![1, 2],
+        events.iter().map(|e| e.sequence).collect::<Vec<_>>()
+    );
+    let (events, _) = event_store.load_page("test_id_A", 2, 2).await;
+    assert_eq!(
+        vec![5],
+        events.iter().map(|e| e.sequence).collect::<Vec<_>>()
+    );
+    let (events, _) = event_store.load_page("test_id_A", 3, 2).await;
+    assert!(events.is_empty());
+    let (events, total) = event_store.load_page("test_id_B", 0, 2).await;
+    assert!(events.is_empty());
+    assert_eq!(0, total);
+}
+
+#[test]
+fn event_envelope_deduplication_test() {
+    let created = TestEvent::Created(Created {
+        id: "test_id_A".to_string(),
+    });
+    let tested = TestEvent::Tested(Tested {
+        test_name: "test A".to_string(),
+    });
+    let envelope = |sequence: usize, payload: &TestEvent| {
+        TestEventEnvelope::new_with_metadata(
+            "test_id_A".to_string(),
+            sequence,
+            "TestAggregate".to_string(),
+            payload.clone(),
+            metadata(),
+        )
+    };
+    let redelivered = |sequence: usize, payload: &TestEvent| {
+        envelope(sequence, payload).with_metadata_entry(TIME_METADATA_KEY, "2021-03-18T12:32:45Z")
+    };
+
+    let mut identities: HashSet<EventIdentity> = HashSet::new();
+    assert!(identities.insert(envelope(1, &created).identity()));
+    assert!(identities.insert(envelope(2, &tested).identity()));
+    assert!(!identities.insert(redelivered(1, &created).identity()));
+    assert!(!identities.insert(redelivered(2, &tested).identity()));
+    assert!(identities.insert(envelope(3, &tested).identity()));
+    assert_eq!(3, identities.len());
+
+    let mut events: HashSet<TestEventEnvelope> = HashSet::new();
+    assert!(events.insert(envelope(1, &created)));
+    assert!(!events.insert(envelope(1, &created)));
+    assert!(events.insert(redelivered(1, &created)));
+    assert_eq!(2, events.len());
+}
+
+struct StrictTestCommandHandler;
+
+impl CommandHandler<TestAggregate> for StrictTestCommandHandler {
+    fn handle(
+        &self,
+        aggregate: &TestAggregate,
+        command: TestCommand,
+    ) -> Result<Vec<TestEvent>, AggregateError> {
+        match command {
+            TestCommand::CreateTest(_) if !aggregate.id.is_empty() => {
+                Err(AggregateError::new("test already created"))
+            }
+            command => aggregate.handle(command),
+        }
+    }
+}
+
+#[tokio::test]
+async fn framework_command_handler_test() {
+    let id = "test_id_A";
+    let command = || TestCommand::CreateTest(CreateTest { id: id.to_string() });
+    assert!(StrictTestCommandHandler
+        .handle(&TestAggregate::default(), command())
+        .is_ok());
+
+    let cqrs = CqrsFramework::new_with_command_handler(
+        MemStore::<TestAggregate>::default(),
+        vec![],
+        StrictTestCommandHandler,
+    );
+    cqrs.execute(id, command()).await.unwrap();
+    let err = cqrs.execute(id, command()).await.unwrap_err();
+    assert_eq!(AggregateError::new("test already created"), err);
+}
+
+#[tokio::test]
+async fn mem_store_count_events_test() {
+    let mut seed_events = HashMap::new();
+    for (id, count) in [("test_id_A", 3), ("test_id_B", 2)] {
+        seed_events.insert(
+            id.to_string(),
+            (0..count)
+                .map(|i| {
+                    TestEvent::Tested(Tested {
+                        test_name: format!("test {}", i),
+                    })
+                })
+                .collect(),
+        );
+    }
+    let event_store = MemStore::<TestAggregate>::with_seed_events(seed_events);
+    assert_eq!(5, event_store.count_events().await);
+    assert_eq!(0, MemStore::<TestAggregate>::default().count_events().await);
+}
+
+#[tokio::test]
+async fn filtered_query_test() {
+    let delivered_events = Default::default();
+    let view = FilteredQuery::new(
+        TestView::new(Arc::clone(&delivered_events)),
+        |event: &TestEventEnvelope| matches!(event.payload, TestEvent::Created(_)),
+    );
+    let cqrs = CqrsFramework::new(MemStore::<TestAggregate>::default(), vec![Arc::new(view)]);
+    let id = "test_id_A";
+
+    cqrs.execute(
+        id,
+        TestCommand::CreateTest(CreateTest { id: id.to_string() }),
+    )
+    .await
+    .unwrap();
+    cqrs.execute(
+        id,
+        TestCommand::ConfirmTest(ConfirmTest {
+            test_name: "test A".to_string(),
+        }),
+    )
+    .await
+    .unwrap();
+
+    let delivered_events = delivered_events.read().unwrap();
+    assert_eq!(1, delivered_events.len());
+    assert_eq!("Created", delivered_events[0].event_type);
+}
+
+#[tokio::test]
+async fn framework_execute_and_return_version_test() {
+    let cqrs = CqrsFramework::new(MemStore::<TestAggregate>::default(), vec![]);
+    let id = "test_id_A";
+
+    let version = cqrs
+        .execute_and_return_version(
+            id,
+            TestCommand::CreateTest(CreateTest { id: id.to_string() }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(1, version);
+    let version = cqrs
+        .execute_and_return_version(
+            id,
+            TestCommand::ConfirmTest(ConfirmTest {
+                test_name: "test A".to_string(),
+            }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(2, version);
+}
+
+struct TestDashboardQuery {
+    activity: Arc<RwLock<Vec<String>>>,
+}
+
+#[async_trait]
+impl ErasedQuery for TestDashboardQuery {
+    async fn dispatch_erased(
+        &self,
+        aggregate_type: &str,
+        aggregate_id: &str,
+        events: &[Box<dyn Any + Send + Sync>],
+    ) {
+        let mut activity = self.activity.write().unwrap();
+        for event in downcast_events::<TestAggregate>(events) {
+            activity.push(format!("{} {}", aggregate_id, event.event_type));
+        }
+        for event in downcast_events::<Customer>(events) {
+            activity.push(format!(
+                "{} {} {}",
+                aggregate_type, aggregate_id, event.event_type
+            ));
+        }
+    }
+}
+
+#[tokio::test]
+async fn multi_aggregate_query_test() {
+    let activity: Arc<RwLock<Vec<String>>> = Default::default();
+    let query = Arc::new(MultiAggregateQuery::new(TestDashboardQuery {
+        activity: Arc::clone(&activity),
+    }));
+    let test_cqrs = CqrsFramework::new(MemStore::<TestAggregate>::default(), vec![query.clone()]);
+    let customer_cqrs = CqrsFramework::new(MemStore::<Customer>::default(), vec![query]);
+
+    test_cqrs
+        .execute(
+            "test_id_A",
+            TestCommand::CreateTest(CreateTest {
+                id: "test_id_A".to_string(),
+            }),
+        )
+        .await
+        .unwrap();
+    customer_cqrs
+        .execute(
+            "customer_A",
+            CustomerCommand::AddCustomerName {
+                changed_name: "John Doe".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        vec![
+            "test_id_A Created".to_string(),
+            "customer customer_A NameAdded".to_string()
+        ],
+        *activity.read().unwrap()
+    );
+}
+
+#[tokio::test]
+async fn mem_store_transaction_test() {
+    let event_store = MemStore::<TestAggregate>::default();
+    let created = |id: &str| {
+        vec![TestEventEnvelope::new(
+            id.to_string(),
+            1,
+            "TestAggregate".to_string(),
+            TestEvent::Created(Created { id: id.to_string() }),
+        )]
+    };
+
+    let stored_aggregates = event_store.transaction(|events| {
+        events.insert("test_id_A".to_string(), created("test_id_A"));
+        events.insert("test_id_B".to_string(), created("test_id_B"));
+        events.len()
+    });
+
+    assert_eq!(2, stored_aggregates);
+    assert_eq!(1, event_store.load("test_id_B").await.len());
+}
+
+#[cfg(feature = "actix-web")]
+#[test]
+fn aggregate_error_response_test() {
+    use actix_web::body::MessageBody;
+    use actix_web::http::StatusCode;
+    use actix_web::ResponseError;
+
+    assert_eq!(
+        StatusCode::BAD_REQUEST,
+        AggregateError::new("test already performed").status_code()
+    );
+    assert_eq!(
+        StatusCode::CONFLICT,
+        AggregateError::AggregateConflict.status_code()
+    );
+    assert_eq!(
+        StatusCode::NOT_FOUND,
+        AggregateError::NotFound("test_id_A".to_string())
+            .context("confirming test")
+            .status_code()
+    );
+    assert_eq!(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        AggregateError::TechnicalError("connection refused".to_string()).status_code()
+    );
+    let _: actix_web::Error = AggregateError::AggregateConflict.into();
+
+    let body = AggregateError::TechnicalError("db down".to_string())
+        .error_response()
+        .into_body()
+        .try_into_bytes()
+        .unwrap();
+    assert!(!String::from_utf8_lossy(&body).contains("db down"));
+}
+
+#[tokio::test]
+async fn logging_event_store_test() {
+    let store = LoggingEventStore::new(MemStore::<TestAggregate>::default());
+    let cqrs = CqrsFramework::new(store, vec![]);
+
+    cqrs.execute(
+        "test_id_A",
+        TestCommand::CreateTest(CreateTest {
+            id: "test_id_A".to_string(),
+        }),
+    )
+    .await
+    .unwrap();
+    let aggregate = cqrs.load_and_return_aggregate("test_id_A").await.unwrap();
+    assert_eq!("test_id_A", aggregate.id);
+}
+
+#[test]
+fn mem_store_event_count_for_type_test() {
+    let mut seed_events = HashMap::new();
+    seed_events.insert(
+        "test_id_A".to_string(),
+        vec![
+            TestEvent::Created(Created {
+                id: "test_id_A".to_string(),
+            }),
+            TestEvent::Tested(Tested {
+                test_name: "test A".to_string(),
+            }),
+            TestEvent::Tested(Tested {
+                test_name: "test B".to_string(),
+            }),
+        ],
+    );
+    let event_store = MemStore::<TestAggregate>::with_seed_events(seed_events);
+
+    assert_eq!(2, event_store.event_count_for_type("test_id_A", "Tested"));
+    assert_eq!(1, event_store.event_count_for_type("test_id_A", "Created"));
+    assert_eq!(
+        0,
+        event_store.event_count_for_type("test_id_A", "SomethingElse")
+    );
+    assert_eq!(0, event_store.event_count_for_type("test_id_B", "Tested"));
+}
+
+#[test]
+fn aggregate_error_serialization_test() {
+    let mut params = HashMap::new();
+    params.insert("test_name".to_string(), "test A".to_string());
+    let errors = vec![
+        AggregateError::UserError(UserErrorPayload {
+            code: Some("ALREADY_PERFORMED".to_string()),
+            message: Some("test already performed".to_string()),
+            params: Some(params),
+        }),
+        AggregateError::AggregateConflict,
+        AggregateError::TechnicalError("connection refused".to_string()),
+        AggregateError::NotFound("test_id_A".to_string()).context("confirming test"),
+        AggregateError::ValidationError(ValidationError::for_field(
+            "test_name",
+            "test name must not be empty",
+        )),
+        AggregateError::ValidationError(ValidationError::new("missing test")),
+        AggregateError::DuplicateCommand("command_A".to_string()),
+    ];
+    for error in errors {
+        let json = serde_json::to_value(&error).unwrap();
+        let deserialized: AggregateError = serde_json::from_value(json).unwrap();
+        assert_eq!(error, deserialized);
+    }
+
+    let json = serde_json::to_value(AggregateError::NotFound("test_id_A".to_string())).unwrap();
+    assert_eq!(
+        serde_json::json!({
+            "variant": "NotFound",
+            "message": "aggregate not found: test_id_A",
+            "fields": {"aggregate_id": "test_id_A"}
+        }),
+        json
+    );
+    assert!(serde_json::from_str::<AggregateError>(r#"{"variant":"Unknown"}"#).is_err());
+}
+
+impl VersionedAggregate for TestAggregate {
+    fn schema_version() -> u32 {
+        2
+    }
+}
+
+#[test]
+fn snapshot_envelope_test() {
+    let mut aggregate = TestAggregate::default();
+    aggregate.apply(TestEvent::Created(Created {
+        id: "test_id_A".to_string(),
+    }));
+
+    let snapshot = SnapshotEnvelope::new(&aggregate).unwrap();
+    assert_eq!(2, snapshot.schema_version);
+    let restored: TestAggregate = snapshot.restore().unwrap();
+    assert_eq!("test_id_A", restored.id);
+
+    let stale_snapshot = SnapshotEnvelope {
+        schema_version: 1,
+        ..snapshot
+    };
+    assert!(!stale_snapshot.is_current::<TestAggregate>());
+    assert!(stale_snapshot.restore::<TestAggregate>().is_none());
+}
+
+struct FailingEventStore {
+    store: MemStore<TestAggregate>,
+}
+
+#[async_trait]
+impl EventStore<TestAggregate> for FailingEventStore {
+    type AC = <MemStore<TestAggregate> as EventStore<TestAggregate>>::AC;
+
+    async fn load(&self, aggregate_id: &str) -> Vec<TestEventEnvelope> {
+        self.store.load(aggregate_id).await
+    }
+
+    async fn load_aggregate(&self, aggregate_id: &str) -> Self::AC {
+        self.store.load_aggregate(aggregate_id).await
+    }
+
+    async fn commit(
+        &self,
+        _events: Vec<TestEvent>,
+        _context: Self::AC,
+        _metadata: HashMap<String, String>,
+    ) -> Result<Vec<TestEventEnvelope>, AggregateError> {
+        Err(AggregateError::TechnicalError(
+            "store unavailable".to_string(),
+        ))
+    }
+}
+
+#[tokio::test]
+async fn mirrored_event_store_test() {
+    let primary = MemStore::<TestAggregate>::default();
+    let secondary = MemStore::<TestAggregate>::default();
+    let primary_events = primary.get_events();
+    let secondary_events = secondary.get_events();
+    let cqrs = CqrsFramework::new(MirroredEventStore::new(primary, secondary), vec![]);
+    let id = "test_id_A";
+
+    cqrs.execute_with_metadata(
+        id,
+        TestCommand::CreateTest(CreateTest { id: id.to_string() }),
+        metadata(),
+    )
+    .await
+    .unwrap();
+
+    let primary_events = primary_events.read().unwrap();
+    let secondary_events = secondary_events.read().unwrap();
+    assert_eq!(1, primary_events.get(id).unwrap().len());
+    let mirrored_event = &secondary_events.get(id).unwrap()[0];
+    assert_eq!(1, mirrored_event.sequence);
+    assert_eq!(metadata(), mirrored_event.metadata);
+}
+
+#[tokio::test]
+async fn mirrored_event_store_secondary_failure_test() {
+    let failing = || FailingEventStore {
+        store: MemStore::default(),
+    };
+    let command = |id: &str| TestCommand::CreateTest(CreateTest { id: id.to_string() });
+
+    let store = MirroredEventStore::new(MemStore::<TestAggregate>::default(), failing());
+    let cqrs = CqrsFramework::new(store, vec![]);
+    cqrs.execute("test_id_A", command("test_id_A"))
+        .await
+        .unwrap();
+
+    let store = MirroredEventStore::new(MemStore::<TestAggregate>::default(), failing())
+        .propagate_secondary_failures();
+    let cqrs = CqrsFramework::new(store, vec![]);
+    let err = cqrs
+        .execute("test_id_A", command("test_id_A"))
+        .await
+        .unwrap_err();
+    assert!(err.is_technical_error());
+
+    let store = MirroredEventStore::new(failing(), MemStore::<TestAggregate>::default());
+    let cqrs = CqrsFramework::new(store, vec![]);
+    let err = cqrs
+        .execute("test_id_A", command("test_id_A"))
+        .await
+        .unwrap_err();
+    assert!(err.is_technical_error());
+}
+
+#[tokio::test]
+async fn mirrored_event_store_out_of_sync_test() {
+    let command = |id: &str| TestCommand::CreateTest(CreateTest { id: id.to_string() });
+    let id = "test_id_A";
+    let secondary = Arc::new(MemStore::<TestAggregate>::default());
+    let context = secondary.load_aggregate(id).await;
+    secondary
+        .commit(
+            vec![TestEvent::Created(Created { id: id.to_string() })],
+            context,
+            metadata(),
+        )
+        .await
+        .unwrap();
+
+    let store = MirroredEventStore::new(MemStore::<TestAggregate>::default(), secondary.clone());
+    let cqrs = CqrsFramework::new(store, vec![]);
+    cqrs.execute(id, command(id)).await.unwrap();
+    assert_eq!(1, secondary.load(id).await.len());
+
+    let store = MirroredEventStore::new(MemStore::<TestAggregate>::default(), secondary.clone())
+        .propagate_secondary_failures();
+    let cqrs = CqrsFramework::new(store, vec![]);
+    let err = cqrs.execute(id, command(id)).await.unwrap_err();
+    assert!(err.is_technical_error());
+    assert_eq!(1, secondary.load(id).await.len());
+}
+
+struct SlowTestQuery {
+    completed: Arc<RwLock<bool>>,
+}
+
+#[async_trait]
+impl Query<TestAggregate> for SlowTestQuery {
+    async fn dispatch(&self, _aggregate_id: &str, _events: &[TestEventEnvelope]) {
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        *self.completed.write().unwrap() = true;
+    }
+}
+
+#[tokio::test]
+async fn query_timeout_test() {
+    let completed = Arc::new(RwLock::new(false));
+    let query = SlowTestQuery {
+        completed: completed.clone(),
+    };
+    let cqrs = CqrsFramework::new(MemStore::<TestAggregate>::default(), vec![Arc::new(query)])
+        .with_query_timeout(Duration::from_millis(10));
+    let id = "test_id_A";
+
+    let start = Instant::now();
+    cqrs.execute(
+        id,
+        TestCommand::CreateTest(CreateTest { id: id.to_string() }),
+    )
+    .await
+    .unwrap();
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert!(!*completed.read().unwrap());
+}
+
+#[test]
+fn given_snapshot_test() {
+    let aggregate = TestAggregate {
+        id: "test_id_A".to_string(),
+        description: "".to_string(),
+        tests: vec!["test A".to_string()],
+    };
+    let snapshot = take_snapshot(&aggregate, 2);
+    assert_eq!(2, snapshot.version);
+    let (restored, version) = restore_from_snapshot(take_snapshot(&aggregate, 2));
+    assert_eq!(2, version);
+    assert_eq!(vec!["test A".to_string()], restored.tests);
+
+    TestFramework::<TestAggregate>::default()
+        .given_snapshot(snapshot)
+        .then_given(vec![TestEvent::Tested(Tested {
+            test_name: "test B".to_string(),
+        })])
+        .when(TestCommand::ConfirmTest(ConfirmTest {
+            test_name: "test A".to_string(),
+        }))
+        .then_expect_error("test already performed");
+}
+
+#[test]
+fn mem_store_find_aggregates_with_event_type_test() {
+    let tested = |test_name: &str| {
+        TestEvent::Tested(Tested {
+            test_name: test_name.to_string(),
+        })
+    };
+    let created = |id: &str| TestEvent::Created(Created { id: id.to_string() });
+    let mut seed_events = HashMap::new();
+    seed_events.insert(
+        "test_id_A".to_string(),
+        vec![created("test_id_A"), tested("test A"), tested("test B")],
+    );
+    seed_events.insert(
+        "test_id_B".to_string(),
+        vec![created("test_id_B"), tested("test A")],
+    );
+    seed_events.insert("test_id_C".to_string(), vec![created("test_id_C")]);
+    let event_store = MemStore::<TestAggregate>::with_seed_events(seed_events);
+
+    let mut found = event_store.find_aggregates_with_event_type("Tested");
+    found.sort();
+    assert_eq!(
+        vec!["test_id_A".to_string(), "test_id_B".to_string()],
+        found
+    );
+    assert_eq!(
+        3,
+        event_store.find_aggregates_with_event_type("Created").len()
+    );
+    assert!(event_store
+        .find_aggregates_with_event_type("SomethingElse")
+        .is_empty());
+}
+
+#[test]
+fn event_envelope_display_test() {
+    let envelope = EventEnvelope::<TestAggregate>::new(
+        "test_id_A".to_string(),
+        1,
+        "TestAggregate".to_string(),
+        TestEvent::Tested(Tested {
+            test_name: "secret test name".to_string(),
+        }),
+    );
+    let display = envelope.to_string();
+    assert!(display.starts_with("TestAggregate test_id_A:1 Tested v1.0"));
+    assert!(!display.contains("secret test name"));
+
+    let debug = format!("{:?}", envelope);
+    #[cfg(feature = "redacted-debug")]
+    assert!(!debug.contains("secret test name"));
+    #[cfg(not(feature = "redacted-debug"))]
+    assert!(debug.contains("secret test name"));
+}
+
+#[tokio::test]
+async fn execute_for_new_aggregate_test() {
+    let event_store = MemStore::<TestAggregate>::default();
+    let stored_events = event_store.get_events();
+    let cqrs = CqrsFramework::new(event_store, vec![]);
+
+    let first_id = cqrs
+        .execute_for_new_aggregate(TestCommand::CreateTest(CreateTest {
+            id: "test A".to_string(),
+        }))
+        .await
+        .unwrap();
+    let second_id = cqrs
+        .execute_for_new_aggregate(TestCommand::CreateTest(CreateTest {
+            id: "test B".to_string(),
+        }))
+        .await
+        .unwrap();
+
+    assert_ne!(first_id, second_id);
+    let stored_events = stored_events.read().unwrap();
+    assert_eq!(1, stored_events.get(&first_id).unwrap().len());
+    assert_eq!(1, stored_events.get(&second_id).unwrap().len());
+}
+
+fn name_customer_for_test(event: &TestEventEnvelope) -> Option<(String, CustomerCommand)> {
+    match &event.payload {
+        TestEvent::Created(created) => Some((
+            format!("customer_{}", created.id),
+            CustomerCommand::AddCustomerName {
+                changed_name: created.id.clone(),
+            },
+        )),
+        _ => None,
+    }
+}
+
+#[tokio::test]
+async fn double_dispatch_test() {
+    let customer_store = MemStore::<Customer>::default();
+    let customer_events = customer_store.get_events();
+    let customer_cqrs = Arc::new(CqrsFramework::new(customer_store, vec![]));
+    let double_dispatch = DoubleDispatch::new(customer_cqrs, name_customer_for_test);
+    let cqrs = CqrsFramework::new(
+        MemStore::<TestAggregate>::default(),
+        vec![Arc::new(double_dispatch)],
+    );
+    let id = "test_id_A";
+
+    cqrs.execute(
+        id,
+        TestCommand::CreateTest(CreateTest { id: id.to_string() }),
+    )
+    .await
+    .unwrap();
+    cqrs.execute(
+        id,
+        TestCommand::ConfirmTest(ConfirmTest {
+            test_name: "test A".to_string(),
+        }),
+    )
+    .await
+    .unwrap();
+
+    let customer_events = customer_events.read().unwrap();
+    assert_eq!(1, customer_events.len());
+    assert_eq!(1, customer_events.get("customer_test_id_A").unwrap().len());
+}
+
+#[tokio::test]
+async fn aggregate_context_serialization_test() {
+    let event_store = MemStore::<TestAggregate>::default();
+    let id = "test_id_A";
+    let context = event_store.load_aggregate(id).await;
+    event_store
+        .commit(
+            vec![
+                TestEvent::Created(Created { id: id.to_string() }),
+                TestEvent::Tested(Tested {
+                    test_name: "test A".to_string(),
+                }),
+            ],
+            context,
+            HashMap::new(),
+        )
+        .await
+        .unwrap();
+    let context = event_store.load_aggregate(id).await;
+
+    let json = serde_json::to_string(&context).unwrap();
+    let restored: MemStoreAggregateContext<TestAggregate> = serde_json::from_str(&json).unwrap();
+    assert_eq!(id, restored.aggregate_id);
+    assert_eq!(2, restored.current_sequence);
+    assert_eq!(vec!["test A".to_string()], restored.aggregate().tests);
+
+    let events = event_store
+        .commit(
+            vec![TestEvent::Tested(Tested {
+                test_name: "test B".to_string(),
+            })],
+            restored,
+            HashMap::new(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(3, events[0].sequence);
+}
+
+#[tokio::test]
+async fn last_event_version_test() {
+    let mut seed_events = HashMap::new();
+    seed_events.insert(
+        "test_id_A".to_string(),
+        vec![
+            TestEvent::Created(Created {
+                id: "test_id_A".to_string(),
+            }),
+            TestEvent::Tested(Tested {
+                test_name: "test A".to_string(),
+            }),
+        ],
+    );
+    let event_store = MemStore::<TestAggregate>::with_seed_events(seed_events);
+    assert_eq!(Some(2), event_store.last_event_version("test_id_A").await);
+    assert_eq!(None, event_store.last_event_version("test_id_B").await);
+
+    let counting_store = CommitCountingEventStore::new(event_store, Default::default());
+    assert_eq!(
+        Some(2),
+        counting_store.last_event_version("test_id_A").await
+    );
+    assert_eq!(None, counting_store.last_event_version("test_id_B").await);
+}
+
+#[tokio::test]
+async fn event_filter_test() {
+    let event_store = MemStore::<TestAggregate>::default();
+    let id = "test_id_A";
+    let tested = |test_name: &str| {
+        TestEvent::Tested(Tested {
+            test_name: test_name.to_string(),
+        })
+    };
+    let context = event_store.load_aggregate(id).await;
+    event_store
+        .commit(
+            vec![
+                TestEvent::Created(Created { id: id.to_string() }),
+                tested("test A"),
+                tested("test B"),
+            ],
+            context,
+            HashMap::new(),
+        )
+        .await
+        .unwrap();
+    let context = event_store.load_aggregate(id).await;
+    event_store
+        .commit(vec![tested("test C")], context, metadata())
+        .await
+        .unwrap();
+    let sequences = |filter: EventFilter<TestAggregate>| -> Vec<usize> {
+        event_store
+            .load_filtered_structured(id, &filter)
+            .iter()
+            .map(|envelope| envelope.sequence)
+            .collect()
+    };
+
+    assert_eq!(vec![1, 2, 3, 4], sequences(EventFilter::new()));
+    assert_eq!(
+        vec![2, 3, 4],
+        sequences(EventFilter::new().event_type("Tested"))
+    );
+    assert_eq!(
+        vec![3],
+        sequences(EventFilter::new().after_version(2).before_version(4))
+    );
+    assert_eq!(
+        vec![4],
+        sequences(EventFilter::new().with_metadata_key("time", "2021-03-18T12:32:45.930Z"))
+    );
+    assert_eq!(
+        vec![2],
+        sequences(
+            EventFilter::new().matching(|envelope| match &envelope.payload {
+                TestEvent::Tested(tested) => tested.test_name == "test A",
+                _ => false,
+            })
+        )
+    );
+    assert_eq!(
+        vec![1, 4],
+        sequences(
+            EventFilter::new()
+                .event_type("Created")
+                .or(EventFilter::new().after_version(3))
+        )
+    );
+    assert_eq!(
+        vec![2],
+        sequences(
+            EventFilter::new()
+                .event_type("Tested")
+                .and(EventFilter::new().before_version(3))
+        )
+    );
+    assert!(event_store
+        .load_filtered_structured("test_id_B", &EventFilter::new())
+        .is_empty());
+}
+
+#[test]
+fn then_expect_state_test() {
+    TestFramework::<TestAggregate>::default()
+        .given(vec![TestEvent::Created(Created {
+            id: "test_id_A".to_string(),
+        })])
+        .when(TestCommand::ConfirmTest(ConfirmTest {
+            test_name: "test A".to_string(),
+        }))
+        .then_expect_state(
+            |aggregate| aggregate.id == "test_id_A" && aggregate.tests == vec!["test A"],
+            "test A is recorded",
+        );
+}
+
+#[test]
+#[should_panic(expected = "unexpected aggregate state: test B is recorded")]
+fn then_expect_state_failure_test() {
+    TestFramework::<TestAggregate>::default()
+        .given_no_previous_events()
+        .when(TestCommand::ConfirmTest(ConfirmTest {
+            test_name: "test A".to_string(),
+        }))
+        .then_expect_state(
+            |aggregate| aggregate.tests.contains(&"test B".to_string()),
+            "test B is recorded",
+        );
+}
+
+#[tokio::test]
+async fn subscribe_test() {
+    let cqrs = CqrsFramework::new(MemStore::<TestAggregate>::default(), vec![]);
+    let id = "test_id_A";
+    cqrs.execute(
+        id,
+        TestCommand::CreateTest(CreateTest { id: id.to_string() }),
+    )
+    .await
+    .unwrap();
+
+    let mut receiver = cqrs.subscribe();
+    cqrs.execute(
+        id,
+        TestCommand::ConfirmTest(ConfirmTest {
+            test_name: "test A".to_string(),
+        }),
+    )
+    .await
+    .unwrap();
+
+    let event = receiver.recv().await.unwrap();
+    assert_eq!(id, event.aggregate_id);
+    assert_eq!(2, event.sequence);
+    assert_eq!("Tested", event.event_type);
+    assert!(receiver.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn mem_store_rollback_last_commit_test() {
+    let event_store = MemStore::<TestAggregate>::default();
+    let stored_events = event_store.get_events();
+    let id = "test_id_A";
+    let mut command_metadata = HashMap::new();
+    command_metadata.insert(COMMAND_ID_METADATA_KEY.to_string(), "command_B".to_string());
+    let tested = || {
+        vec![TestEvent::Tested(Tested {
+            test_name: "test A".to_string(),
+        })]
+    };
+    let context = event_store.load_aggregate(id).await;
+    event_store
+        .commit(
+            vec![TestEvent::Created(Created { id: id.to_string() })],
+            context,
+            HashMap::new(),
+        )
+        .await
+        .unwrap();
+    let context = event_store.load_aggregate(id).await;
+    event_store
+        .commit(tested(), context, command_metadata.clone())
+        .await
+        .unwrap();
+
+    let context = event_store.load_aggregate(id).await;
+    let err = event_store
+        .commit(tested(), context, command_metadata.clone())
+        .await
+        .unwrap_err();
+    assert_eq!(
+        AggregateError::DuplicateCommand("command_B".to_string()),
+        err
+    );
+    assert!(event_store.is_duplicate_command(id, "command_B").await);
+    assert!(
+        !event_store
+            .is_duplicate_command("test_id_B", "command_B")
+            .await
+    );
+
+    let err = event_store.rollback_last_commit(id, 3).unwrap_err();
+    assert!(err.is_technical_error());
+    assert_eq!(2, stored_events.read().unwrap().get(id).unwrap().len());
+
+    event_store.rollback_last_commit(id, 1).unwrap();
+    assert_eq!(1, stored_events.read().unwrap().get(id).unwrap().len());
+
+    // the rolled back command may be committed again
+    let context = event_store.load_aggregate(id).await;
+    let events = event_store
+        .commit(tested(), context, command_metadata)
+        .await
+        .unwrap();
+    assert_eq!(2, events[0].sequence);
+    assert_eq!(2, stored_events.read().unwrap().get(id).unwrap().len());
+
+    event_store.rollback_last_commit(id, 2).unwrap();
+    assert!(stored_events.read().unwrap().get(id).is_none());
+}
+
+struct TestCommandValidator;
+
+impl CommandValidator<TestAggregate> for TestCommandValidator {
+    fn validate(&self, command: &TestCommand) -> Result<(), ValidationError> {
+        match command {
+            TestCommand::ConfirmTest(confirm) if confirm.test_name.is_empty() => Err(
+                ValidationError::for_field("test_name", "test name must not be empty"),
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[tokio::test]
+async fn command_validator_test() {
+    let event_store = MemStore::<TestAggregate>::default();
+    let stored_events = event_store.get_events();
+    let cqrs = CqrsFramework::new(event_store, vec![]).with_validator(TestCommandValidator);
+    let id = "test_id_A";
+    let confirm = |test_name: &str| {
+        TestCommand::ConfirmTest(ConfirmTest {
+            test_name: test_name.to_string(),
+        })
+    };
+
+    let err = cqrs.execute(id, confirm("")).await.unwrap_err();
+    assert_eq!(
+        AggregateError::ValidationError(ValidationError::for_field(
+            "test_name",
+            "test name must not be empty"
+        )),
+        err
+    );
+    assert_eq!(
+        "invalid test_name: test name must not be empty",
+        err.to_string()
+    );
+    assert!(stored_events.read().unwrap().is_empty());
+
+    cqrs.execute(id, confirm("test A")).await.unwrap();
+    assert_eq!(1, stored_events.read().unwrap().get(id).unwrap().len());
+}
+
+#[tokio::test]
+async fn mem_store_with_capacity_test() {
+    let event_store = MemStore::<TestAggregate>::with_capacity(100);
+    let stored_events = event_store.get_events();
+    assert!(stored_events.read().unwrap().capacity() >= 100);
+
+    let cqrs = CqrsFramework::new(event_store, vec![]);
+    let id = "test_id_A";
+    cqrs.execute(
+        id,
+        TestCommand::CreateTest(CreateTest { id: id.to_string() }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(1, stored_events.read().unwrap().get(id).unwrap().len());
+}
+
+#[tokio::test]
+async fn load_stream_test() {
+    let mut seed_events = HashMap::new();
+    seed_events.insert(
+        "test_id_A".to_string(),
+        vec![
+            TestEvent::Created(Created {
+                id: "test_id_A".to_string(),
+            }),
+            TestEvent::Tested(Tested {
+                test_name: "test A".to_string(),
+            }),
+        ],
+    );
+    let event_store = MemStore::<TestAggregate>::with_seed_events(seed_events);
+    let sequences: Vec<usize> = event_store
+        .load_stream("test_id_A")
+        .map(|envelope| envelope.sequence)
+        .collect()
+        .await;
+    assert_eq!(vec![1, 2], sequences);
+    assert_eq!(None, event_store.load_stream("test_id_B").next().await);
+
+    let counting_store = CommitCountingEventStore::new(event_store, Default::default());
+    let sequences: Vec<usize> = counting_store
+        .load_stream("test_id_A")
+        .map(|envelope| envelope.sequence)
+        .collect()
+        .await;
+    assert_eq!(vec![1, 2], sequences);
+}
+
+#[test]
+fn event_envelope_occurred_at_test() {
+    let envelope = |metadata: HashMap<String, String>| {
+        EventEnvelope::<TestAggregate>::new_with_metadata(
+            "test_id_A".to_string(),
+            1,
+            "TestAggregate".to_string(),
+            TestEvent::Created(Created {
+                id: "test_id_A".to_string(),
+            }),
+            metadata,
+        )
+    };
+    let occurred_at = envelope(metadata()).occurred_at().unwrap();
+    assert_eq!("2021-03-18T12:32:45.930+00:00", occurred_at.to_rfc3339());
+
+    let mut offset_metadata = HashMap::new();
+    offset_metadata.insert(
+        TIME_METADATA_KEY.to_string(),
+        "2021-03-18T14:32:45.930+02:00".to_string(),
+    );
+    assert_eq!(Some(occurred_at), envelope(offset_metadata).occurred_at());
+
+    assert_eq!(None, envelope(HashMap::new()).occurred_at());
+    let mut malformed_metadata = HashMap::new();
+    malformed_metadata.insert(TIME_METADATA_KEY.to_string(), "yesterday".to_string());
+    assert_eq!(None, envelope(malformed_metadata).occurred_at());
+}
+
+#[tokio::test]
+async fn command_audit_log_test() {
+    let audit_log = CommandAuditLog::<TestAggregate>::default();
+    let error_log = audit_log.clone();
+    let cqrs = CqrsFramework::new(
+        MemStore::<TestAggregate>::default(),
+        vec![Arc::new(audit_log.clone())],
+    )
+    .with_error_observer(move |aggregate_id, err| error_log.record_error(aggregate_id, err));
+    let id = "test_id_A";
+    let confirm = || {
+        TestCommand::ConfirmTest(ConfirmTest {
+            test_name: "test A".to_string(),
+        })
+    };
+
+    cqrs.execute(
+        id,
+        TestCommand::CreateTest(CreateTest { id: id.to_string() }),
+    )
+    .await
+    .unwrap();
+    cqrs.execute(id, confirm()).await.unwrap();
+    cqrs.execute(id, confirm()).await.unwrap_err();
+
+    let entries = audit_log.entries();
+    assert_eq!(3, entries.len());
+    assert!(entries.iter().all(|entry| entry.aggregate_id == id));
+    assert_eq!(
+        vec![1, 1, 0],
+        entries
+            .iter()
+            .map(|entry| entry.event_count)
+            .collect::<Vec<usize>>()
+    );
+    assert_eq!(None, entries[1].error);
+    assert!(entries[2]
+        .error
+        .as_ref()
+        .unwrap()
+        .contains("test already performed"));
+    assert!(entries[0].timestamp <= entries[2].timestamp);
+}
+
+#[test]
+fn aggregate_error_equality_test() {
+    fn assert_eq_impl<T: Eq>(_: &T) {}
+    let err = AggregateError::new_with_code("test already performed", "ALREADY_PERFORMED");
+    assert_eq_impl(&err);
+
+    assert_eq!(
+        AggregateError::new_with_code("test already performed", "ALREADY_PERFORMED"),
+        err
+    );
+    assert_ne!(AggregateError::new("test already performed"), err);
+    assert_eq!(
+        AggregateError::TechnicalError("connection refused".to_string()),
+        AggregateError::TechnicalError("connection refused".to_string())
+    );
+    assert_ne!(
+        AggregateError::ValidationError(ValidationError::for_field("test_name", "required")),
+        AggregateError::ValidationError(ValidationError::new("required"))
+    );
+    assert_eq!(
+        err.clone().context("confirming test"),
+        err.clone().context("confirming test")
+    );
+    assert_ne!(err.clone().context("confirming test"), err);
+}
+
+struct DelayedTestQuery;
+
+#[async_trait]
+impl Query<TestAggregate> for DelayedTestQuery {
+    async fn dispatch(&self, _aggregate_id: &str, _events: &[TestEventEnvelope]) {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+#[tokio::test]
+async fn graceful_shutdown_test() {
+    let event_store = MemStore::<TestAggregate>::default();
+    let stored_events = event_store.get_events();
+    let cqrs = CqrsFramework::new(event_store, vec![Arc::new(DelayedTestQuery)]);
+    let id = "test_id_A";
+
+    let (result, in_flight) = tokio::join!(
+        cqrs.execute(
+            id,
+            TestCommand::CreateTest(CreateTest { id: id.to_string() })
+        ),
+        async {
+            let handle = cqrs.shutdown_signal();
+            let in_flight = handle.in_flight();
+            tokio::time::timeout(Duration::from_secs(1), handle)
+                .await
+                .unwrap();
+            in_flight
+        }
+    );
+    assert_eq!(1, in_flight);
+    result.unwrap();
+    assert_eq!(1, stored_events.read().unwrap().get(id).unwrap().len());
+
+    let err = cqrs
+        .execute(
+            id,
+            TestCommand::ConfirmTest(ConfirmTest {
+                test_name: "test A".to_string(),
+            }),
+        )
+        .await
+        .unwrap_err();
+    assert!(err.is_technical_error());
+    assert_eq!(1, stored_events.read().unwrap().get(id).unwrap().len());
+    tokio::time::timeout(Duration::from_secs(1), cqrs.shutdown_signal())
+        .await
+        .unwrap();
+}
+
+#[test]
+fn then_expect_error_containing_test() {
+    TestFramework::<TestAggregate>::default()
+        .given(vec![TestEvent::Tested(Tested {
+            test_name: "test A".to_string(),
+        })])
+        .when(TestCommand::ConfirmTest(ConfirmTest {
+            test_name: "test A".to_string(),
+        }))
+        .then_expect_error_containing("already performed");
+}
+
+#[test]
+#[should_panic(
+    expected = "expected error containing 'not found', received error: 'test already performed'"
+)]
+fn then_expect_error_containing_failure_test() {
+    TestFramework::<TestAggregate>::default()
+        .given(vec![TestEvent::Tested(Tested {
+            test_name: "test A".to_string(),
+        })])
+        .when(TestCommand::ConfirmTest(ConfirmTest {
+            test_name: "test A".to_string(),
+        }))
+        .then_expect_error_containing("not found");
+}
+
+#[tokio::test]
+async fn shared_event_store_test() {
+    let event_store = Arc::new(MemStore::<TestAggregate>::default());
+    let cqrs = CqrsFramework::new(event_store.clone(), vec![]);
+    let id = "test_id_A";
+
+    cqrs.execute(
+        id,
+        TestCommand::CreateTest(CreateTest { id: id.to_string() }),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(1, event_store.count_events().await);
+    assert_eq!(Some(1), event_store.last_event_version(id).await);
+    assert_eq!(vec![id.to_string()], event_store.aggregate_ids());
+}
+
+#[tokio::test]
+async fn execute_many_test() {
+    let event_store = MemStore::<TestAggregate>::default();
+    let stored_events = event_store.get_events();
+    let cqrs = CqrsFramework::new(event_store, vec![]).with_validator(TestCommandValidator);
+    let create = |id: &str| {
+        (
+            id.to_string(),
+            TestCommand::CreateTest(CreateTest { id: id.to_string() }),
+        )
+    };
+    let invalid = (
+        "test_id_B".to_string(),
+        TestCommand::ConfirmTest(ConfirmTest {
+            test_name: "".to_string(),
+        }),
+    );
+
+    let results = cqrs
+        .execute_many(vec![create("test_id_A"), invalid, create("test_id_C")])
+        .await;
+
+    assert_eq!(3, results.len());
+    assert!(results[0].is_ok());
+    assert!(matches!(
+        results[1],
+        Err(AggregateError::ValidationError(_))
+    ));
+    assert!(results[2].is_ok());
+    let stored_events = stored_events.read().unwrap();
+    assert!(stored_events.contains_key("test_id_A"));
+    assert!(!stored_events.contains_key("test_id_B"));
+    assert!(stored_events.contains_key("test_id_C"));
+}
+
+#[test]
+fn aggregate_error_user_message_test() {
+    assert_eq!(
+        Some("test already performed"),
+        AggregateError::new("test already performed").user_message()
+    );
+    assert_eq!(
+        Some("test already performed"),
+        AggregateError::new("test already performed")
+            .context("confirming test")
+            .user_message()
+    );
+    assert_eq!(
+        Some("required"),
+        AggregateError::ValidationError(ValidationError::for_field("test_name", "required"))
+            .user_message()
+    );
+    let no_message = AggregateError::UserError(UserErrorPayload {
+        code: Some("ALREADY_PERFORMED".to_string()),
+        message: None,
+        params: None,
+    });
+    assert_eq!(None, no_message.user_message());
+    assert_eq!(None, AggregateError::AggregateConflict.user_message());
+    assert_eq!(
+        None,
+        AggregateError::TechnicalError("connection refused".to_string()).user_message()
+    );
+    assert_eq!(
+        None,
+        AggregateError::NotFound("test_id_A".to_string()).user_message()
+    );
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+struct TestsPerformedView {
+    tests: Vec<String>,
+}
+
+struct TestsPerformedProjection {
+    views: MemReadModelStore<TestsPerformedView>,
+}
+
+#[async_trait]
+impl Query<TestAggregate> for TestsPerformedProjection {
+    async fn dispatch(&self, aggregate_id: &str, events: &[TestEventEnvelope]) {
+        let mut view = self.views.load(aggregate_id).await.unwrap_or_default();
+        for event in events {
+            if let TestEvent::Tested(tested) = &event.payload {
+                view.tests.push(tested.test_name.clone());
+            }
+        }
+        self.views.save(aggregate_id, &view).await;
+    }
+}
+
+#[tokio::test]
+async fn mem_read_model_store_test() {
+    let views = MemReadModelStore::<TestsPerformedView>::default();
+    let projection = TestsPerformedProjection {
+        views: views.clone(),
+    };
+    let cqrs = CqrsFramework::new(
+        MemStore::<TestAggregate>::default(),
+        vec![Arc::new(projection)],
+    );
+    let id = "test_id_A";
+    for test_name in ["test A", "test B"] {
+        cqrs.execute(
+            id,
+            TestCommand::ConfirmTest(ConfirmTest {
+                test_name: test_name.to_string(),
+            }),
+        )
+        .await
+        .unwrap();
+    }
+
+    assert_eq!(
+        Some(TestsPerformedView {
+            tests: vec!["test A".to_string(), "test B".to_string()]
+        }),
+        views.load(id).await
+    );
+    assert_eq!(None, views.load("test_id_B").await);
+    assert_eq!(1, views.get_views().read().unwrap().len());
+
+    views.delete(id).await;
+    assert_eq!(None, views.load(id).await);
+}
+
+#[test]
+fn then_expect_events_in_any_order_test() {
+    TestFramework::<TestAggregate>::default()
+        .given_no_previous_events()
+        .when(TestCommand::ConfirmTest(ConfirmTest {
+            test_name: "test A".to_string(),
+        }))
+        .then_expect_events_in_any_order(vec![TestEvent::Tested(Tested {
+            test_name: "test A".to_string(),
+        })]);
+}
+
+#[test]
+#[should_panic(expected = "expected events in any order")]
+fn then_expect_events_in_any_order_failure_test() {
+    TestFramework::<TestAggregate>::default()
+        .given_no_previous_events()
+        .when(TestCommand::ConfirmTest(ConfirmTest {
+            test_name: "test A".to_string(),
+        }))
+        .then_expect_events_in_any_order(vec![
+            TestEvent::Tested(Tested {
+                test_name: "test A".to_string(),
+            }),
+            TestEvent::Tested(Tested {
+                test_name: "test A".to_string(),
+            }),
+        ]);
+}
+
+#[tokio::test]
+async fn checkpointed_query_test() {
+    let dispatched = Arc::new(RwLock::new(Vec::new()));
+    let checkpoints = MemCheckpointStore::default();
+    let query = Checkpointed::new(TestView::new(dispatched.clone()), checkpoints.clone());
+    let id = "test_id_A";
+    let envelope = |sequence: usize| {
+        EventEnvelope::<TestAggregate>::new(
+            id.to_string(),
+            sequence,
+            "TestAggregate".to_string(),
+            TestEvent::Tested(Tested {
+                test_name: format!("test {}", sequence),
+            }),
+        )
+    };
+
+    query.dispatch(id, &[envelope(1), envelope(2)]).await;
+    assert_eq!(Some(2), checkpoints.load_checkpoint(id).await);
+
+    // a rebuild replaying all events resumes after the checkpoint
+    query
+        .dispatch(id, &[envelope(1), envelope(2), envelope(3)])
+        .await;
+    query.dispatch(id, &[envelope(2)]).await;
+    let sequences: Vec<usize> = dispatched
+        .read()
+        .unwrap()
+        .iter()
+        .map(|event| event.sequence)
+        .collect();
+    assert_eq!(vec![1, 2, 3], sequences);
+    assert_eq!(Some(3), checkpoints.load_checkpoint(id).await);
+    assert_eq!(None, checkpoints.load_checkpoint("test_id_B").await);
+}
+
+#[test]
+fn event_envelope_with_metadata_entry_test() {
+    let envelope = EventEnvelope::<TestAggregate>::new_with_metadata(
+        "test_id_A".to_string(),
+        1,
+        "TestAggregate".to_string(),
+        TestEvent::Created(Created {
+            id: "test_id_A".to_string(),
+        }),
+        metadata(),
+    )
+    .with_metadata_entry("user", "alice")
+    .with_metadata_entry(TIME_METADATA_KEY, "2021-03-18T12:40:00.000Z".to_string());
+
+    assert_eq!(2, envelope.metadata.len());
+    assert_eq!("alice", envelope.metadata["user"]);
+    assert_eq!(
+        "2021-03-18T12:40:00.000Z",
+        envelope.metadata[TIME_METADATA_KEY]
+    );
+}
+
+#[test]
+fn domain_event_aggregate_changes_test() {
+    let event = TestEvent::Tested(Tested {
+        test_name: "test A".to_string(),
+    });
+    assert_eq!(vec!["tests"], event.aggregate_changes());
+
+    let event = CustomerEvent::NameAdded {
+        changed_name: "John Doe".to_string(),
+    };
+    assert!(event.aggregate_changes().is_empty());
+}
+
+struct TestCompensator;
+
+impl Compensator<TestAggregate> for TestCompensator {
+    fn compensate(&self, event: &TestEventEnvelope) -> Option<(String, TestCommand)> {
+        match &event.payload {
+            TestEvent::Tested(tested) => Some((
+                event.aggregate_id.clone(),
+                TestCommand::DoSomethingElse(DoSomethingElse {
+                    description: format!("reverted {}", tested.test_name),
+                }),
+            )),
+            _ => None,
+        }
+    }
+}
+
+#[tokio::test]
+async fn compensating_command_runner_test() {
+    let event_store = MemStore::<TestAggregate>::default();
+    let stored_events = event_store.get_events();
+    let cqrs = CqrsFramework::new(event_store, vec![]);
+    let id = "test_id_A";
+    cqrs.execute(
+        id,
+        TestCommand::CreateTest(CreateTest { id: id.to_string() }),
+    )
+    .await
+    .unwrap();
+    for test_name in ["test A", "test B"] {
+        cqrs.execute(
+            id,
+            TestCommand::ConfirmTest(ConfirmTest {
+                test_name: test_name.to_string(),
+            }),
+        )
+        .await
+        .unwrap();
+    }
+    let committed = stored_events.read().unwrap()[id].clone();
+
+    let results = CompensatingCommandRunner::new(TestCompensator)
+        .run(&cqrs, &committed)
+        .await;
+
+    assert_eq!(2, results.len());
+    assert!(results
+        .iter()
+        .all(|(aggregate_id, result)| aggregate_id == id && result.is_ok()));
+    let descriptions: Vec<String> = stored_events.read().unwrap()[id]
+        .iter()
+        .filter_map(|event| match &event.payload {
+            TestEvent::SomethingElse(something_else) => Some(something_else.description.clone()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(vec!["reverted test B", "reverted test A"], descriptions);
+}
+
+#[test]
+fn query_name_test() {
+    let query: Arc<dyn Query<TestAggregate>> = Arc::new(TestView::new(Default::default()));
+    assert!(query.name().ends_with("TestView"));
+
+    let filtered = FilteredQuery::new(
+        TestView::new(Default::default()),
+        |_: &TestEventEnvelope| true,
+    );
+    assert!(filtered.name().ends_with("TestView"));
+
+    let weak = WeakConsistencyQuery::new(TestView::new(Default::default()));
+    assert!(Query::<TestAggregate>::name(&weak).ends_with("TestView"));
+
+    let checkpointed = Checkpointed::new(
+        TestView::new(Default::default()),
+        MemCheckpointStore::default(),
+    );
+    assert!(Query::<TestAggregate>::name(&checkpointed).ends_with("TestView"));
+
+    let multi = MultiAggregateQuery::new(TestDashboardQuery {
+        activity: Default::default(),
+    });
+    assert!(Query::<TestAggregate>::name(&multi).ends_with("TestDashboardQuery"));
+}
+
+impl<ES: EventStore<TestAggregate>> BulkEventStore<TestAggregate> for CommitCountingEventStore<ES> {}
+
+#[tokio::test]
+async fn bulk_event_store_test() {
+    let mut seed_events = HashMap::new();
+    for id in ["test_id_A", "test_id_B"] {
+        seed_events.insert(
+            id.to_string(),
+            vec![TestEvent::Created(Created { id: id.to_string() })],
+        );
+    }
+    let event_store = MemStore::<TestAggregate>::with_seed_events(seed_events);
+
+    let events = event_store
+        .load_many(&["test_id_A", "test_id_B", "test_id_C"])
+        .await;
+    assert_eq!(3, events.len());
+    assert_eq!(1, events["test_id_A"].len());
+    assert_eq!(1, events["test_id_B"].len());
+    assert!(events["test_id_C"].is_empty());
+
+    let counting_store = CommitCountingEventStore::new(event_store, Default::default());
+    let events = counting_store.load_many(&["test_id_B", "test_id_C"]).await;
+    assert_eq!(2, events.len());
+    assert_eq!("test_id_B", events["test_id_B"][0].aggregate_id);
+    assert!(events["test_id_C"].is_empty());
+}
+
+#[test]
+fn aggregate_error_from_json_error_test() {
+    fn deserialize_event(payload: &str) -> Result<TestEvent, AggregateError> {
+        Ok(serde_json::from_str(payload)?)
+    }
+    assert!(deserialize_event(r#"{"Created":{"id":"test_id_A"}}"#).is_ok());
+
+    let err = deserialize_event(r#"{"Created":"#).unwrap_err();
+    assert!(err.is_technical_error());
+    assert!(err.to_string().contains("EOF"));
+
+    let err = deserialize_event(r#"{"Unknown":{}}"#).unwrap_err();
+    assert!(err.is_technical_error());
+    assert!(err.to_string().contains("unknown variant"));
+}
+
+#[test]
+fn when_and_apply_test() {
+    let (aggregate, result) = TestFramework::<TestAggregate>::default()
+        .given(vec![TestEvent::Created(Created {
+            id: "test_id_A".to_string(),
+        })])
+        .when_and_apply(TestCommand::ConfirmTest(ConfirmTest {
+            test_name: "test A".to_string(),
+        }));
+    assert_eq!(1, result.unwrap().len());
+    assert_eq!("test_id_A", aggregate.id);
+    assert_eq!(vec!["test A".to_string()], aggregate.tests);
+
+    let (aggregate, result) = TestFramework::<TestAggregate>::default()
+        .given_snapshot(take_snapshot(&aggregate, 2))
+        .when_and_apply(TestCommand::ConfirmTest(ConfirmTest {
+            test_name: "test A".to_string(),
+        }));
+    assert_eq!(
+        AggregateError::new("test already performed"),
+        result.unwrap_err()
+    );
+    assert_eq!(vec!["test A".to_string()], aggregate.tests);
+}
+
+#[tokio::test]
+async fn mem_store_subscribe_to_commits_test() {
+    let event_store = MemStore::<TestAggregate>::default();
+    let mut commits = event_store.subscribe_to_commits();
+    let cqrs = CqrsFramework::new(event_store, vec![]);
+    let id = "test_id_A";
+    let command_context = || CommandContext::default().with_command_id("command_A");
+
+    cqrs.execute_with_context(
+        id,
+        TestCommand::CreateTest(CreateTest { id: id.to_string() }),
+        command_context(),
+    )
+    .await
+    .unwrap();
+    let committed_events = commits.recv().await.unwrap();
+    assert_eq!(1, committed_events.len());
+    assert_eq!(id, committed_events[0].aggregate_id);
+
+    // a duplicate commit is not broadcast
+    cqrs.execute_with_context(
+        id,
+        TestCommand::CreateTest(CreateTest { id: id.to_string() }),
+        command_context(),
+    )
+    .await
+    .unwrap();
+    assert!(commits.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn try_execute_test() {
+    let id = "test_id_A";
+    let create = || TestCommand::CreateTest(CreateTest { id: id.to_string() });
+    let confirm = |test_name: &str| {
+        TestCommand::ConfirmTest(ConfirmTest {
+            test_name: test_name.to_string(),
+        })
+    };
+
+    let cqrs = CqrsFramework::new(MemStore::<TestAggregate>::default(), vec![])
+        .with_validator(TestCommandValidator);
+    cqrs.try_execute(id, create()).await.unwrap();
+    match cqrs.try_execute(id, confirm("")).await.unwrap_err() {
+        CommandDispatchError::Domain(AggregateError::ValidationError(_)) => {}
+        err => panic!("expected a domain error, found: {}", err),
+    }
+
+    let store = FailingEventStore {
+        store: MemStore::default(),
+    };
+    let cqrs = CqrsFramework::new(store, vec![]);
+    let err = cqrs.try_execute(id, create()).await.unwrap_err();
+    assert_eq!(
+        CommandDispatchError::Store(AggregateError::TechnicalError(
+            "store unavailable".to_string()
+        )),
+        err
+    );
+    assert!(AggregateError::from(err).is_technical_error());
+
+    let query = SlowTestQuery {
+        completed: Arc::new(RwLock::new(false)),
+    };
+    let event_store = MemStore::<TestAggregate>::default();
+    let stored_events = event_store.get_events();
+    let cqrs = CqrsFramework::new(event_store, vec![Arc::new(query)])
+        .with_query_timeout(Duration::from_millis(10));
+    let err = cqrs.try_execute(id, create()).await.unwrap_err();
+    assert_eq!(
+        CommandDispatchError::Query(vec![QueryError::Timeout(Duration::from_millis(10))]),
+        err
+    );
+    assert_eq!(1, stored_events.read().unwrap().get(id).unwrap().len());
+
+    let cqrs = CqrsFramework::new(MemStore::<TestAggregate>::default(), vec![]);
+    let _ = cqrs.shutdown_signal();
+    match cqrs.try_execute(id, create()).await.unwrap_err() {
+        CommandDispatchError::ShuttingDown(_) => {}
+        err => panic!("expected a shutdown rejection, found: {}", err),
+    }
+}
+
+#[tokio::test]
+async fn tenant_aware_event_store_test() {
+    let event_store = Arc::new(MemStore::<TestAggregate>::default());
+    let stored_events = event_store.get_events();
+    let acme_store = TenantAwareEventStore::new(event_store.clone(), TenantContext::new("acme"));
+    let globex_store = TenantAwareEventStore::new(event_store, TenantContext::new("globex"));
+    let id = "test_id_A";
+    let create = || TestCommand::CreateTest(CreateTest { id: id.to_string() });
+
+    let acme_cqrs = CqrsFramework::new(acme_store, vec![]);
+    acme_cqrs.execute(id, create()).await.unwrap();
+    let globex_cqrs = CqrsFramework::new(globex_store, vec![]);
+    globex_cqrs.execute(id, create()).await.unwrap();
+    globex_cqrs
+        .execute(
+            id,
+            TestCommand::ConfirmTest(ConfirmTest {
+                test_name: "test A".to_string(),
+            }),
+        )
+        .await
+        .unwrap();
+
+    let acme_store = TenantAwareEventStore::new(
+        MemStore::<TestAggregate>::default(),
+        TenantContext::new("acme"),
+    );
+    assert!(acme_store.load(id).await.is_empty());
+
+    let stored_events = stored_events.read().unwrap();
+    assert!(stored_events.get(id).is_none());
+    assert_eq!(1, stored_events.get("acme/test_id_A").unwrap().len());
+    assert_eq!(2, stored_events.get("globex/test_id_A").unwrap().len());
+    let acme_event = &stored_events.get("acme/test_id_A").unwrap()[0];
+    assert_eq!(
+        Some(TenantContext::new("acme")),
+        TenantContext::from_metadata(&acme_event.metadata)
+    );
+}
+
+#[tokio::test]
+async fn tenant_aware_event_store_collision_test() {
+    let event_store = Arc::new(MemStore::<TestAggregate>::default());
+    let stored_events = event_store.get_events();
+    let acme_store = TenantAwareEventStore::new(event_store.clone(), TenantContext::new("acme"));
+    let acme_x_store =
+        TenantAwareEventStore::new(event_store.clone(), TenantContext::new("acme/x"));
+
+    let context = acme_store.load_aggregate("x/y").await;
+    acme_store
+        .commit(
+            vec![TestEvent::Created(Created {
+                id: "x/y".to_string(),
+            })],
+            context,
+            HashMap::default(),
+        )
+        .await
+        .unwrap();
+    assert!(acme_x_store.load("y").await.is_empty());
+    assert_eq!(None, acme_x_store.last_event_version("y").await);
+
+    let context = acme_x_store.load_aggregate("y").await;
+    acme_x_store
+        .commit(
+            vec![TestEvent::Created(Created {
+                id: "y".to_string(),
+            })],
+            context,
+            HashMap::default(),
+        )
+        .await
+        .unwrap();
+    let acme_events = acme_store.load("x/y").await;
+    assert_eq!(1, acme_events.len());
+    assert_eq!("x/y", acme_events[0].aggregate_id);
+    let acme_x_events = acme_x_store.load("y").await;
+    assert_eq!(1, acme_x_events.len());
+    assert_eq!("y", acme_x_events[0].aggregate_id);
+
+    let stored_events = stored_events.read().unwrap();
+    assert!(stored_events.contains_key("acme/x/y"));
+    assert!(stored_events.contains_key("acme%2Fx/y"));
+}
+
+#[tokio::test]
+async fn tenant_aware_event_store_load_test() {
+    let event_store = Arc::new(MemStore::<TestAggregate>::default());
+    let acme_store = TenantAwareEventStore::new(event_store.clone(), TenantContext::new("acme"));
+    let globex_store = TenantAwareEventStore::new(event_store, TenantContext::new("globex"));
+    let id = "test_id_A";
+
+    let context = acme_store.load_aggregate(id).await;
+    let committed_events = acme_store
+        .commit(
+            vec![TestEvent::Created(Created { id: id.to_string() })],
+            context,
+            HashMap::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(id, committed_events[0].aggregate_id);
+
+    let acme_events = acme_store.load(id).await;
+    assert_eq!(1, acme_events.len());
+    assert_eq!(id, acme_events[0].aggregate_id);
+    assert_eq!(Some(1), acme_store.last_event_version(id).await);
+    assert_eq!(1, acme_store.load_stream(id).count().await);
+
+    assert!(globex_store.load(id).await.is_empty());
+    assert_eq!(None, globex_store.last_event_version(id).await);
+}
+
+#[tokio::test]
+async fn command_rate_limiter_test() {
+    let cqrs = CqrsFramework::new(MemStore::<TestAggregate>::default(), vec![])
+        .with_rate_limiter(CommandRateLimiter::new(2, Duration::from_secs(3600)));
+    let confirm = |test_name: &str| {
+        TestCommand::ConfirmTest(ConfirmTest {
+            test_name: test_name.to_string(),
+        })
+    };
+
+    cqrs.execute("test_id_A", confirm("test A")).await.unwrap();
+    cqrs.execute("test_id_A", confirm("test B")).await.unwrap();
+    let err = cqrs
+        .execute("test_id_A", confirm("test C"))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        AggregateError::new_with_code("rate limit exceeded", "RATE_LIMIT_EXCEEDED"),
+        err
+    );
+    cqrs.execute("test_id_B", confirm("test A")).await.unwrap();
+
+    let limiter = CommandRateLimiter::new(1, Duration::from_millis(20));
+    limiter.check("test_id_A").unwrap();
+    assert!(limiter.check("test_id_A").is_err());
+    std::thread::sleep(Duration::from_millis(25));
+    limiter.check("test_id_A").unwrap();
+}
+
+#[tokio::test]
+async fn deduplicating_event_store_test() {
+    let commits = Arc::new(RwLock::new(0));
+    let event_store = DeduplicatingEventStore::new(CommitCountingEventStore::new(
+        MemStore::<TestAggregate>::default(),
+        Arc::clone(&commits),
+    ));
+    let id = "test_id_A";
+    let mut metadata = metadata();
+    metadata.insert(COMMAND_ID_METADATA_KEY.to_string(), "command-1".to_string());
+
+    let context = event_store.load_aggregate(id).await;
+    let committed_events = event_store
+        .commit(
+            vec![TestEvent::Created(Created { id: id.to_string() })],
+            context,
+            metadata.clone(),
+        )
+        .await
+        .unwrap();
+    let context = event_store.load_aggregate(id).await;
+    let err = event_store
+        .commit(
+            vec![TestEvent::Created(Created { id: id.to_string() })],
+            context,
+            metadata.clone(),
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(
+        AggregateError::DuplicateCommand("command-1".to_string()),
+        err
+    );
+    assert_eq!(1, *commits.read().unwrap());
+    assert_eq!(committed_events, event_store.load(id).await);
+    assert!(event_store.is_duplicate_command(id, "command-1").await);
+
+    // the same command ID may be used for a different aggregate instance
+    let context = event_store.load_aggregate("test_id_B").await;
+    event_store
+        .commit(
+            vec![TestEvent::Created(Created {
+                id: "test_id_B".to_string(),
+            })],
+            context,
+            metadata,
+        )
+        .await
+        .unwrap();
+    assert_eq!(2, *commits.read().unwrap());
+
+    let context = event_store.load_aggregate(id).await;
+    event_store
+        .commit(
+            vec![TestEvent::Tested(Tested {
+                test_name: "test A".to_string(),
+            })],
+            context,
+            HashMap::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(3, *commits.read().unwrap());
+}
+
+#[tokio::test]
+async fn deduplicating_event_store_framework_test() {
+    let commits = Arc::new(RwLock::new(0));
+    let event_store = DeduplicatingEventStore::new(CommitCountingEventStore::new(
+        MemStore::<TestAggregate>::default(),
+        Arc::clone(&commits),
+    ));
+    let view_events: Arc<RwLock<Vec<EventEnvelope<TestAggregate>>>> = Default::default();
+    let query = TestView::new(view_events.clone());
+    let cqrs = CqrsFramework::new(event_store, vec![Arc::new(query)]);
+    let id = "test_id_A";
+    let command = || {
+        CommandEnvelope::new(TestCommand::CreateTest(CreateTest { id: id.to_string() }))
+            .with_command_id("command-1".to_string())
+    };
+
+    cqrs.execute_envelope(id, command()).await.unwrap();
+    cqrs.execute_envelope(id, command()).await.unwrap();
+
+    assert_eq!(1, *commits.read().unwrap());
+    let view_events = view_events.read().unwrap();
+    assert_eq!(1, view_events.len());
+    assert_eq!(1, view_events[0].sequence);
+}
+
+#[tokio::test]
+async fn error_observer_all_failures_test() {
+    let errors = Arc::new(RwLock::new(Vec::new()));
+    let observed_errors = Arc::clone(&errors);
+    let cqrs = CqrsFramework::new(MemStore::<TestAggregate>::default(), vec![])
+        .with_error_observer(move |aggregate_id, err| {
+            observed_errors
+                .write()
+                .unwrap()
+                .push((aggregate_id.to_string(), err.clone()))
+        });
+    let confirm = || {
+        TestCommand::ConfirmTest(ConfirmTest {
+            test_name: "test A".to_string(),
+        })
+    };
+
+    cqrs.execute_if_exists("test_id_A", confirm())
+        .await
+        .unwrap_err();
+    let _ = cqrs.shutdown_signal();
+    cqrs.execute("test_id_B", confirm()).await.unwrap_err();
+
+    let errors = errors.read().unwrap();
+    assert_eq!(2, errors.len());
+    assert_eq!(
+        (
+            "test_id_A".to_string(),
+            AggregateError::NotFound("test_id_A".to_string())
+        ),
+        errors[0]
+    );
+    assert_eq!("test_id_B", errors[1].0);
+    assert!(errors[1].1.is_technical_error());
+}
+
+#[test]
+fn json_event_serializer_test() {
+    let serializer = JsonEventSerializer::<TestEvent>::default();
+    let event = TestEvent::Tested(Tested {
+        test_name: "test A".to_string(),
+    });
+
+    let bytes = serializer.serialize(&event).unwrap();
+    assert_eq!(
+        event,
+        serializer.deserialize(&bytes, "Tested", "1.0").unwrap()
+    );
+
+    let err = serializer
+        .deserialize(b"not json", "Tested", "1.0")
+        .unwrap_err();
+    assert!(AggregateError::from(err).is_technical_error());
+}
+
+#[test]
+fn assert_aggregate_state_after_events_test() {
+    ThisTestFramework::assert_aggregate_state_after_events(
+        vec![
+            TestEvent::Created(Created {
+                id: "test_id_A".to_string(),
+            }),
+            TestEvent::Tested(Tested {
+                test_name: "test A".to_string(),
+            }),
+        ],
+        |aggregate| {
+            assert_eq!("test_id_A", aggregate.id);
+            assert_eq!(vec!["test A".to_string()], aggregate.tests);
+        },
+    );
+}
+
+// Only the ID is carried by the compaction event, so compaction succeeds only for streams of
+// `Created` events.
+impl CompactableAggregate for TestAggregate {
+    fn compaction_event(&self) -> TestEvent {
+        TestEvent::Created(Created {
+            id: self.id.clone(),
+        })
+    }
+}
+
+#[tokio::test]
+async fn compacting_event_store_test() {
+    let created = |id: &str| TestEvent::Created(Created { id: id.to_string() });
+    let mut seed_events = HashMap::new();
+    seed_events.insert(
+        "test_id_A".to_string(),
+        vec![created("first"), created("second"), created("third")],
+    );
+    seed_events.insert(
+        "test_id_B".to_string(),
+        vec![
+            created("first"),
+            TestEvent::Tested(Tested {
+                test_name: "test A".to_string(),
+            }),
+            created("second"),
+        ],
+    );
+    let store =
+        CompactingEventStore::new(MemStore::<TestAggregate>::with_seed_events(seed_events), 1);
+
+    assert!(store.compact::<TestAggregate>("test_id_A").await.unwrap());
+    let events = store.load("test_id_A").await;
+    assert_eq!(2, events.len());
+    assert_eq!(created("second"), events[0].payload);
+    assert_eq!(2, events[0].sequence);
+    assert_eq!(created("third"), events[1].payload);
+    assert_eq!(3, events[1].sequence);
+    assert!(!store.compact::<TestAggregate>("test_id_A").await.unwrap());
+
+    let cqrs = CqrsFramework::new(store, vec![]);
+    cqrs.execute(
+        "test_id_A",
+        TestCommand::ConfirmTest(ConfirmTest {
+            test_name: "test A".to_string(),
+        }),
+    )
+    .await
+    .unwrap();
+    let aggregate = cqrs.load_and_return_aggregate("test_id_A").await.unwrap();
+    assert_eq!("third", aggregate.id);
+    assert_eq!(vec!["test A".to_string()], aggregate.tests);
+}
+
+#[tokio::test]
+async fn mem_store_replace_events_test() {
+    let created = |id: &str| TestEvent::Created(Created { id: id.to_string() });
+    let mut seed_events = HashMap::new();
+    seed_events.insert(
+        "test_id_A".to_string(),
+        vec![created("first"), created("second"), created("third")],
+    );
+    let event_store = MemStore::<TestAggregate>::with_seed_events(seed_events);
+    let compaction_event = event_store.load("test_id_A").await[1].clone();
+
+    // an event committed after the events to replace were loaded is retained
+    let context = event_store.load_aggregate("test_id_A").await;
+    event_store
+        .commit(vec![created("fourth")], context, HashMap::default())
+        .await
+        .unwrap();
+    event_store
+        .replace_events("test_id_A", 2, vec![compaction_event.clone()])
+        .await
+        .unwrap();
+    let events = event_store.load("test_id_A").await;
+    assert_eq!(
+        vec![2, 3, 4],
+        events.iter().map(|e| e.sequence).collect::<Vec<_>>()
+    );
+    assert_eq!(created("fourth"), events[2].payload);
+
+    let err = event_store
+        .replace_events("test_id_A", 1, vec![compaction_event.clone()])
+        .await
+        .unwrap_err();
+    assert_eq!(AggregateError::AggregateConflict, err);
+    let err = event_store
+        .replace_events("test_id_B", 1, vec![compaction_event])
+        .await
+        .unwrap_err();
+    assert_eq!(AggregateError::AggregateConflict, err);
+    assert_eq!(3, event_store.load("test_id_A").await.len());
+}
+
+#[tokio::test]
+async fn compacting_event_store_verification_test() {
+    let mut seed_events = HashMap::new();
+    seed_events.insert(
+        "test_id_A".to_string(),
+        vec![
+            TestEvent::Created(Created {
+                id: "test_id_A".to_string(),
+            }),
+            TestEvent::Tested(Tested {
+                test_name: "test A".to_string(),
+            }),
+            TestEvent::Tested(Tested {
+                test_name: "test B".to_string(),
+            }),
+        ],
+    );
+    let store =
+        CompactingEventStore::new(MemStore::<TestAggregate>::with_seed_events(seed_events), 1);
+
+    let err = store
+        .compact::<TestAggregate>("test_id_A")
+        .await
+        .unwrap_err();
+    assert!(err.is_technical_error());
+    assert_eq!(3, store.load("test_id_A").await.len());
+}
+
+#[tokio::test]
+async fn inspect_test() {
+    let transitions = Arc::new(RwLock::new(Vec::new()));
+    let inspected = Arc::clone(&transitions);
+    let cqrs = CqrsFramework::new(MemStore::<TestAggregate>::default(), vec![]).inspect(
+        move |aggregate_id, before: &TestAggregate, after: &TestAggregate| {
+            inspected.write().unwrap().push((
+                aggregate_id.to_string(),
+                before.tests.clone(),
+                after.tests.clone(),
+            ))
+        },
+    );
+    let confirm = |test_name: &str| {
+        TestCommand::ConfirmTest(ConfirmTest {
+            test_name: test_name.to_string(),
+        })
+    };
+
+    cqrs.execute("test_id_A", confirm("test A")).await.unwrap();
+    cqrs.execute("test_id_A", confirm("test B")).await.unwrap();
+    cqrs.execute("test_id_A", confirm("test B"))
+        .await
+        .unwrap_err();
+
+    let transitions = transitions.read().unwrap();
+    assert_eq!(2, transitions.len());
+    assert_eq!(
+        ("test_id_A".to_string(), vec![], vec!["test A".to_string()]),
+        transitions[0]
+    );
+    assert_eq!(
+        (
+            "test_id_A".to_string(),
+            vec!["test A".to_string()],
+            vec!["test A".to_string(), "test B".to_string()]
+        ),
+        transitions[1]
+    );
+}
+
+#[tokio::test]
+async fn get_latest_event_test() {
+    let event_store = MemStore::<TestAggregate>::default();
+    let id = "test_id_A";
+    assert!(event_store.get_latest_event(id).is_none());
+
+    let context = event_store.load_aggregate(id).await;
+    event_store
+        .commit(
+            vec![
+                TestEvent::Created(Created { id: id.to_string() }),
+                TestEvent::Tested(Tested {
+                    test_name: "test A".to_string(),
+                }),
+            ],
+            context,
+            HashMap::default(),
+        )
+        .await
+        .unwrap();
+
+    let latest_event = event_store.get_latest_event(id).unwrap();
+    assert_eq!(2, latest_event.sequence);
+    assert_eq!(
+        TestEvent::Tested(Tested {
+            test_name: "test A".to_string(),
+        }),
+        latest_event.payload
+    );
+    assert!(event_store.get_latest_event("test_id_B").is_none());
+}
+
+#[tokio::test]
+async fn max_events_per_aggregate_test() {
+    let cqrs = CqrsFramework::new(MemStore::<TestAggregate>::default(), vec![])
+        .with_max_events_per_aggregate(2);
+    let confirm = |test_name: &str| {
+        TestCommand::ConfirmTest(ConfirmTest {
+            test_name: test_name.to_string(),
+        })
+    };
+
+    cqrs.execute("test_id_A", confirm("test A")).await.unwrap();
+    cqrs.execute("test_id_A", confirm("test B")).await.unwrap();
+    let err = cqrs
+        .execute("test_id_A", confirm("test C"))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        AggregateError::new_with_code("aggregate too large, please archive", "AGGREGATE_TOO_LARGE"),
+        err
+    );
+    cqrs.execute("test_id_B", confirm("test A")).await.unwrap();
+}
+
+#[tokio::test]
+async fn on_create_test() {
+    let cqrs = CqrsFramework::new(MemStore::<TestAggregate>::default(), vec![]);
+    let created = || {
+        CREATED_AGGREGATES
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|id| id.as_str() == "on_create_id")
+            .count()
+    };
+
+    let create = || {
+        CommandEnvelope::new(TestCommand::CreateTest(CreateTest {
+            id: "on_create_id".to_string(),
+        }))
+        .with_command_id("command_A".to_string())
+    };
+
+    assert_eq!(0, created());
+    cqrs.execute_envelope("on_create_id", create())
+        .await
+        .unwrap();
+    assert_eq!(1, created());
+
+    // a retry of the creating command does not create the aggregate again
+    cqrs.execute_envelope("on_create_id", create())
+        .await
+        .unwrap();
+    assert_eq!(1, created());
+
+    cqrs.execute(
+        "on_create_id",
+        TestCommand::ConfirmTest(ConfirmTest {
+            test_name: "test A".to_string(),
+        }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(1, created());
+}
+
+#[tokio::test]
+async fn mem_store_deep_clone_test() {
+    let event_store = MemStore::<TestAggregate>::default();
+    let id = "test_id_A";
+    let tested = |test_name: &str| {
+        vec![TestEvent::Tested(Tested {
+            test_name: test_name.to_string(),
+        })]
+    };
+    let context = event_store.load_aggregate(id).await;
+    event_store
+        .commit(tested("test A"), context, HashMap::default())
+        .await
+        .unwrap();
+
+    let fork = event_store.deep_clone();
+    let context = fork.load_aggregate(id).await;
+    fork.commit(tested("test B"), context, HashMap::default())
+        .await
+        .unwrap();
+    let context = event_store.load_aggregate(id).await;
+    event_store
+        .commit(tested("test C"), context, HashMap::default())
+        .await
+        .unwrap();
+
+    let original_events = event_store.load(id).await;
+    let forked_events = fork.load(id).await;
+    assert_eq!(2, original_events.len());
+    assert_eq!(2, forked_events.len());
+    assert_eq!(original_events[0], forked_events[0]);
+    assert_eq!(tested("test B")[0], forked_events[1].payload);
+    assert_eq!(tested("test C")[0], original_events[1].payload);
+}
+
+struct TestedV1Migrator;
+
+impl EventMigrator<RawEventEnvelope, TestEventEnvelope> for TestedV1Migrator {
+    fn migrate(&self, mut event: RawEventEnvelope) -> Result<TestEventEnvelope, AggregateError> {
+        if event.event_type == "Tested" && event.event_version == "0.1" {
+            let test_name = event.payload["Tested"]["name"].take();
+            event.payload = serde_json::json!({ "Tested": { "test_name": test_name } });
+            event.event_version = "1.0".to_string();
+        }
+        Ok(event.into_envelope()?)
+    }
+}
+
+#[test]
+fn event_migrator_test() {
+    let current = EventEnvelope::<TestAggregate>::new(
+        "test_id_A".to_string(),
+        1,
+        "TestAggregate".to_string(),
+        TestEvent::Created(Created {
+            id: "test_id_A".to_string(),
+        }),
+    );
+    let mut legacy = RawEventEnvelope::from_envelope(&current).unwrap();
+    legacy.sequence = 2;
+    legacy.event_type = "Tested".to_string();
+    legacy.event_version = "0.1".to_string();
+    legacy.payload = serde_json::json!({ "Tested": { "name": "test A" } });
+    assert!(legacy.clone().into_envelope::<TestAggregate>().is_err());
+
+    let migrated = TestedV1Migrator
+        .migrate_all(vec![
+            RawEventEnvelope::from_envelope(&current).unwrap(),
+            legacy,
+        ])
+        .unwrap();
+    assert_eq!(current, migrated[0]);
+    assert_eq!("1.0", migrated[1].event_version);
+    assert_eq!(
+        TestEvent::Tested(Tested {
+            test_name: "test A".to_string(),
+        }),
+        migrated[1].payload
+    );
+
+    let mut malformed = RawEventEnvelope::from_envelope(&current).unwrap();
+    malformed.payload = serde_json::json!({ "Unknown": {} });
+    assert!(TestedV1Migrator
+        .migrate(malformed)
+        .unwrap_err()
+        .is_technical_error());
+}
+
+#[tokio::test]
+async fn execute_read_only_test() {
+    let cqrs = CqrsFramework::new(MemStore::<TestAggregate>::default(), vec![]);
+    let id = "test_id_A";
+    assert_eq!(
+        AggregateError::NotFound(id.to_string()),
+        cqrs.execute_read_only(id, |aggregate| aggregate.tests.len())
+            .await
+            .unwrap_err()
+    );
+
+    cqrs.execute(
+        id,
+        TestCommand::ConfirmTest(ConfirmTest {
+            test_name: "test A".to_string(),
+        }),
+    )
+    .await
+    .unwrap();
+    let tests = cqrs
+        .execute_read_only(id, |aggregate| aggregate.tests.clone())
+        .await
+        .unwrap();
+    assert_eq!(vec!["test A".to_string()], tests);
+}
+
+#[tokio::test]
+async fn mem_store_transactional_test() {
+    let event_store = MemStore::<TestAggregate>::default();
+    let tested = |test_name: &str| {
+        vec![TestEvent::Tested(Tested {
+            test_name: test_name.to_string(),
+        })]
+    };
+
+    let mut tx = event_store.begin().await;
+    let context = event_store.load_aggregate("test_id_A").await;
+    event_store
+        .commit_in_transaction(&mut tx, tested("test A"), context, HashMap::default())
+        .await
+        .unwrap();
+    let context = event_store.load_aggregate("test_id_A").await;
+    let committed_events = event_store
+        .commit_in_transaction(&mut tx, tested("test B"), context, HashMap::default())
+        .await
+        .unwrap();
+    assert_eq!(2, committed_events[0].sequence);
+    let context = event_store.load_aggregate("test_id_B").await;
+    event_store
+        .commit_in_transaction(&mut tx, tested("test A"), context, HashMap::default())
+        .await
+        .unwrap();
+    assert!(event_store.load("test_id_A").await.is_empty());
+    event_store.commit_transaction(tx).await.unwrap();
+    assert_eq!(2, event_store.load("test_id_A").await.len());
+    assert_eq!(1, event_store.load("test_id_B").await.len());
+
+    let mut tx = event_store.begin().await;
+    let context = event_store.load_aggregate("test_id_C").await;
+    event_store
+        .commit_in_transaction(&mut tx, tested("test A"), context, HashMap::default())
+        .await
+        .unwrap();
+    event_store.rollback_transaction(tx).await;
+    assert!(event_store.load("test_id_C").await.is_empty());
+
+    let mut tx = event_store.begin().await;
+    let context = event_store.load_aggregate("test_id_C").await;
+    event_store
+        .commit_in_transaction(&mut tx, tested("test A"), context, HashMap::default())
+        .await
+        .unwrap();
+    let context = event_store.load_aggregate("test_id_A").await;
+    event_store
+        .commit_in_transaction(&mut tx, tested("test C"), context, HashMap::default())
+        .await
+        .unwrap();
+    let context = event_store.load_aggregate("test_id_A").await;
+    event_store
+        .commit(tested("test D"), context, HashMap::default())
+        .await
+        .unwrap();
+    assert_eq!(
+        AggregateError::AggregateConflict,
+        event_store.commit_transaction(tx).await.unwrap_err()
+    );
+    assert!(event_store.load("test_id_C").await.is_empty());
+    assert_eq!(3, event_store.load("test_id_A").await.len());
+}
+
+#[tokio::test]
+async fn mem_store_transactional_command_id_test() {
+    let event_store = MemStore::<TestAggregate>::default();
+    let id = "test_id_A";
+    let mut command_metadata = HashMap::new();
+    command_metadata.insert(COMMAND_ID_METADATA_KEY.to_string(), "command_A".to_string());
+    let tested = || {
+        vec![TestEvent::Tested(Tested {
+            test_name: "test A".to_string(),
+        })]
+    };
+
+    let mut tx = event_store.begin().await;
+    let context = event_store.load_aggregate(id).await;
+    event_store
+        .commit_in_transaction(&mut tx, tested(), context, command_metadata.clone())
+        .await
+        .unwrap();
+    let context = event_store.load_aggregate(id).await;
+    let err = event_store
+        .commit_in_transaction(&mut tx, tested(), context, command_metadata.clone())
+        .await
+        .unwrap_err();
+    assert_eq!(
+        AggregateError::DuplicateCommand("command_A".to_string()),
+        err
+    );
+    assert!(!event_store.is_duplicate_command(id, "command_A").await);
+    event_store.commit_transaction(tx).await.unwrap();
+    assert!(event_store.is_duplicate_command(id, "command_A").await);
+
+    let context = event_store.load_aggregate(id).await;
+    let err = event_store
+        .commit(tested(), context, command_metadata.clone())
+        .await
+        .unwrap_err();
+    assert_eq!(
+        AggregateError::DuplicateCommand("command_A".to_string()),
+        err
+    );
+
+    let mut tx = event_store.begin().await;
+    let context = event_store.load_aggregate(id).await;
+    let err = event_store
+        .commit_in_transaction(&mut tx, tested(), context, command_metadata)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        AggregateError::DuplicateCommand("command_A".to_string()),
+        err
+    );
+    event_store.rollback_transaction(tx).await;
+    assert_eq!(1, event_store.load(id).await.len());
+
+    // a command rolled back after the transaction may be executed again
+    event_store.rollback_last_commit(id, 1).unwrap();
+    assert!(!event_store.is_duplicate_command(id, "command_A").await);
+}
+
+#[tokio::test]
+async fn mem_store_replay_to_test() {
+    let created = |id: &str| TestEvent::Created(Created { id: id.to_string() });
+    let mut seed_events = HashMap::new();
+    seed_events.insert("test_id_B".to_string(), vec![created("test_id_B")]);
+    seed_events.insert(
+        "test_id_A".to_string(),
+        vec![
+            created("test_id_A"),
+            TestEvent::Tested(Tested {
+                test_name: "test A".to_string(),
+            }),
+        ],
+    );
+    seed_events.insert("other_id_C".to_string(), vec![created("other_id_C")]);
+    let event_store = MemStore::<TestAggregate>::with_seed_events(seed_events);
+
+    let replayed_events = Arc::new(RwLock::new(Vec::new()));
+    let view = PrefixedTestView {
+        prefix: "test_id",
+        view: TestView::new(Arc::clone(&replayed_events)),
+    };
+    event_store.replay_to(&view).await;
+
+    let replayed: Vec<(String, usize)> = replayed_events
+        .read()
+        .unwrap()
+        .iter()
+        .map(|envelope| (envelope.aggregate_id.clone(), envelope.sequence))
+        .collect();
+    assert_eq!(
+        vec![
+            ("test_id_A".to_string(), 1),
+            ("test_id_A".to_string(), 2),
+            ("test_id_B".to_string(), 1),
+        ],
+        replayed
+    );
+}
+
+#[test]
+fn framework_debug_test() {
+    let view = TestView::new(Default::default());
+    let cqrs = CqrsFramework::new(MemStore::<TestAggregate>::default(), vec![Arc::new(view)]);
+    let debug = format!("{:?}", cqrs);
+    assert!(debug.starts_with("CqrsFramework { store: \""));
+    assert!(debug.contains("MemStore<"));
+    assert!(debug.ends_with("query_processors: 1 }"));
+}
+
+#[tokio::test]
+async fn test_event_publisher_test() {
+    let publisher = TestEventPublisher::<TestAggregate>::default();
+    let cqrs = CqrsFramework::new(
+        MemStore::<TestAggregate>::default(),
+        vec![Arc::new(publisher.clone())],
+    );
+    let id = "test_id_A";
+
+    cqrs.execute(
+        id,
+        TestCommand::CreateTest(CreateTest { id: id.to_string() }),
+    )
+    .await
+    .unwrap();
+    cqrs.execute(
+        id,
+        TestCommand::ConfirmTest(ConfirmTest {
+            test_name: "test A".to_string(),
+        }),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(2, publisher.event_count());
+    let events = publisher.events();
+    assert_eq!(1, events[0].sequence);
+    assert_eq!(
+        TestEvent::Tested(Tested {
+            test_name: "test A".to_string(),
+        }),
+        events[1].payload
+    );
+}
+
+#[tokio::test]
+async fn execute_with_timeout_test() {
+    let id = "test_id_A";
+    let create = || TestCommand::CreateTest(CreateTest { id: id.to_string() });
+
+    let cqrs = CqrsFramework::new(MemStore::<TestAggregate>::default(), vec![]);
+    cqrs.execute_with_timeout(id, create(), Duration::from_secs(1))
+        .await
+        .unwrap();
+
+    let query = SlowTestQuery {
+        completed: Arc::new(RwLock::new(false)),
+    };
+    let cqrs = CqrsFramework::new(MemStore::<TestAggregate>::default(), vec![Arc::new(query)]);
+    let start = Instant::now();
+    let err = cqrs
+        .execute_with_timeout(id, create(), Duration::from_millis(10))
+        .await
+        .unwrap_err();
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert_eq!(AggregateError::TechnicalError("timeout".to_string()), err);
+}